@@ -0,0 +1,725 @@
+//! Pure distribution math on plain integers.
+//!
+//! Nothing here touches accounts, CPIs or Anchor types, and only `core`/`alloc` are used,
+//! so the apportionment logic can be unit-, property- and fuzz-tested in isolation.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+/// `conversion_rate` is payout-mint units per this many quote units
+pub const CONVERSION_RATE_SCALE: u128 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+}
+
+/// Outcome of splitting one page's claim across its investors
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DistributionResult {
+    /// Investor share of the claim after f_locked and the daily cap
+    pub investor_fee_quote: u64,
+    /// Floor pro-rata share per investor, in input order
+    pub shares: Vec<u64>,
+    /// Amount actually paid per investor (0 when the share is below `min_payout`)
+    pub payouts: Vec<u64>,
+    pub total_payout: u64,
+    /// Dusted shares plus rounding leftover: `investor_fee_quote - total_payout`
+    pub dust: u64,
+}
+
+/// `bps` basis points of `amount`, floored
+pub fn bps_of(amount: u64, bps: u16) -> Result<u64, MathError> {
+    u64::try_from((amount as u128) * (bps as u128) / BPS_DENOMINATOR).map_err(|_| MathError::Overflow)
+}
+
+/// Whether `part` is more than `bps` basis points of `whole`
+pub fn share_exceeds_bps(part: u64, whole: u64, bps: u16) -> bool {
+    (part as u128) * BPS_DENOMINATOR > (whole as u128) * (bps as u128)
+}
+
+/// Day cumulative after a page pays `page_total`. Checked rather than saturating: a capped
+/// cumulative would under-report and let the daily cap be exceeded.
+pub fn add_distributed(cumulative: u64, page_total: u64) -> Result<u64, MathError> {
+    cumulative.checked_add(page_total).ok_or(MathError::Overflow)
+}
+
+/// Part of `amount` vested linearly over `[start_ts, end_ts)` at `now_ts`; all of it from
+/// `end_ts` on (and for an empty window)
+pub fn linear_vested(amount: u64, start_ts: i64, end_ts: i64, now_ts: i64) -> u64 {
+    if now_ts >= end_ts {
+        return amount;
+    }
+    if now_ts <= start_ts {
+        return 0;
+    }
+    let elapsed = (now_ts - start_ts) as u128;
+    let duration = (end_ts - start_ts) as u128;
+    // elapsed < duration, so the result is below `amount`
+    ((amount as u128) * elapsed / duration) as u64
+}
+
+/// Locked fraction of the baseline in bps, saturating at 100% (0 when `y0` is 0)
+pub fn f_locked_bps(locked_total: u128, y0: u128) -> u64 {
+    // Clamp in u128 before narrowing: locked_total may exceed y0 by far
+    locked_total
+        .saturating_mul(BPS_DENOMINATOR)
+        .checked_div(y0)
+        .map_or(0, |bps| bps.min(BPS_DENOMINATOR) as u64)
+}
+
+/// Share a tier actually pays: its `bps`, capped at the locked fraction
+pub fn eligible_bps(bps: u16, f_locked_bps: u64) -> u16 {
+    core::cmp::min(bps as u64, f_locked_bps) as u16
+}
+
+/// `Σ slice * min(tier_bps, max_bps)` over the parts of `claimed` falling into each tier.
+/// `tiers` are `(start, bps)` pairs with increasing starts; tier `i` covers
+/// `[start_i, start_{i+1})` and the last one everything above its start.
+pub fn tiered_bps_amount(claimed: u64, tiers: &[(u64, u16)], max_bps: u64) -> u128 {
+    let mut total: u128 = 0;
+    for (i, (start, bps)) in tiers.iter().enumerate() {
+        if claimed <= *start {
+            break;
+        }
+        let end = tiers.get(i + 1).map_or(claimed, |(next, _)| (*next).min(claimed));
+        total += (end.saturating_sub(*start) as u128) * (eligible_bps(*bps, max_bps) as u128);
+    }
+    total
+}
+
+/// Investor portion of `claimed`: tiered share scaled by f_locked, then clamped to the daily cap.
+/// A flat share is the single tier `[(0, bps)]`.
+pub fn investor_fee_quote(
+    claimed: u64,
+    y0: u128,
+    share_tiers: &[(u64, u16)],
+    daily_cap: Option<u64>,
+    cumulative_today: u64,
+    locked_total: u128,
+) -> Result<u64, MathError> {
+    // If locked_total == 0, investor_fee_quote must be zero
+    if locked_total == 0 {
+        return Ok(0);
+    }
+    let amount = tiered_bps_amount(claimed, share_tiers, f_locked_bps(locked_total, y0));
+    let mut investor_fee_quote = u64::try_from(amount / BPS_DENOMINATOR).map_err(|_| MathError::Overflow)?;
+
+    if let Some(cap) = daily_cap {
+        investor_fee_quote = investor_fee_quote.min(cap.saturating_sub(cumulative_today));
+    }
+    Ok(investor_fee_quote)
+}
+
+/// Page apportionment: investor share of `effective_claimed`, split pro-rata by
+/// `locked_amounts` with floor rounding and `min_payout` dusting
+pub fn compute_distribution(
+    effective_claimed: u64,
+    y0: u128,
+    share_tiers: &[(u64, u16)],
+    daily_cap: Option<u64>,
+    cumulative_today: u64,
+    min_payout: u64,
+    locked_amounts: &[u64],
+) -> Result<DistributionResult, MathError> {
+    let locked_total: u128 = locked_amounts.iter().map(|l| *l as u128).sum();
+    let investor_fee_quote = investor_fee_quote(
+        effective_claimed,
+        y0,
+        share_tiers,
+        daily_cap,
+        cumulative_today,
+        locked_total,
+    )?;
+    apportion(investor_fee_quote, min_payout, locked_amounts)
+}
+
+/// Split `investor_fee_quote` pro-rata by `locked_amounts` with floor rounding and
+/// `min_payout` dusting; with nothing locked it is all dust
+pub fn apportion(investor_fee_quote: u64, min_payout: u64, locked_amounts: &[u64]) -> Result<DistributionResult, MathError> {
+    // Single-investor raise: the whole quote is the one share, no pro-rata division needed
+    if let [locked] = locked_amounts {
+        let share = if *locked == 0 { 0 } else { investor_fee_quote };
+        let payout = if share > 0 && share >= min_payout { share } else { 0 };
+        return Ok(DistributionResult {
+            investor_fee_quote,
+            shares: vec![share],
+            payouts: vec![payout],
+            total_payout: payout,
+            dust: investor_fee_quote - payout,
+        });
+    }
+    apportion_pro_rata(investor_fee_quote, min_payout, locked_amounts)
+}
+
+/// General path of `apportion`, for any number of investors
+pub fn apportion_pro_rata(investor_fee_quote: u64, min_payout: u64, locked_amounts: &[u64]) -> Result<DistributionResult, MathError> {
+    let locked_total: u128 = locked_amounts.iter().map(|l| *l as u128).sum();
+    let mut result = DistributionResult {
+        investor_fee_quote,
+        shares: vec![0; locked_amounts.len()],
+        payouts: vec![0; locked_amounts.len()],
+        ..Default::default()
+    };
+    if investor_fee_quote == 0 || locked_total == 0 {
+        result.dust = investor_fee_quote;
+        return Ok(result);
+    }
+    for (i, locked) in locked_amounts.iter().enumerate() {
+        // u64 * u64 always fits in u128
+        let numerator = (investor_fee_quote as u128) * (*locked as u128);
+        let share = u64::try_from(numerator / locked_total).map_err(|_| MathError::Overflow)?;
+        result.shares[i] = share;
+        if share >= min_payout {
+            result.payouts[i] = share;
+            result.total_payout = result.total_payout.checked_add(share).ok_or(MathError::Overflow)?;
+        }
+    }
+    // Dusted shares and rounding leftover both stay behind as dust
+    result.dust = investor_fee_quote - result.total_payout;
+    Ok(result)
+}
+
+/// Floor-rounding leftover of `result`: what neither a share nor a dusted share holds
+pub fn rounding_leftover(result: &DistributionResult) -> u64 {
+    result.investor_fee_quote - result.shares.iter().sum::<u64>()
+}
+
+/// Hand the rounding leftover out one unit each to the investors whose pro-rata share lost the
+/// most to flooring (largest remainder; earlier investors win ties), then re-apply `min_payout`
+pub fn assign_largest_remainders(result: &mut DistributionResult, weights: &[u64], min_payout: u64) -> Result<(), MathError> {
+    let weight_total: u128 = weights.iter().map(|w| *w as u128).sum();
+    if weight_total == 0 {
+        return Ok(());
+    }
+    let leftover = rounding_leftover(result) as usize;
+    let quote = result.investor_fee_quote as u128;
+    let mut order: Vec<usize> = (0..weights.len()).filter(|i| weights[*i] > 0).collect();
+    order.sort_by_key(|i| (core::cmp::Reverse(quote * (weights[*i] as u128) % weight_total), *i));
+    // The leftover is below the number of weighted investors, so each gets at most one unit
+    for i in order.into_iter().take(leftover) {
+        result.shares[i] += 1;
+    }
+    refresh_payouts(result, min_payout)
+}
+
+/// Add the whole rounding leftover to investor `index`'s share, then re-apply `min_payout`
+pub fn assign_leftover_to(result: &mut DistributionResult, index: usize, min_payout: u64) -> Result<(), MathError> {
+    let leftover = rounding_leftover(result);
+    result.shares[index] = result.shares[index].checked_add(leftover).ok_or(MathError::Overflow)?;
+    refresh_payouts(result, min_payout)
+}
+
+fn refresh_payouts(result: &mut DistributionResult, min_payout: u64) -> Result<(), MathError> {
+    result.total_payout = 0;
+    for (share, payout) in result.shares.iter().zip(result.payouts.iter_mut()) {
+        *payout = if *share > 0 && *share >= min_payout { *share } else { 0 };
+        result.total_payout = result.total_payout.checked_add(*payout).ok_or(MathError::Overflow)?;
+    }
+    result.dust = result.investor_fee_quote - result.total_payout;
+    Ok(())
+}
+
+/// What bounds one page's investor quote, for `compute_distributable` (`None` = no such cap)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PageCaps<'a> {
+    pub y0: u128,
+    pub share_tiers: &'a [(u64, u16)],
+    pub locked_total: u128,
+    /// Investor payouts the daily cap still allows
+    pub daily_cap_left: Option<u64>,
+    /// Investor payouts the lifetime cap still allows
+    pub lifetime_cap_left: Option<u128>,
+    /// Carry the page may add to the investor quote (0 = none)
+    pub carry: u64,
+    /// Quote the payouts are drawn from
+    pub balance: u64,
+    /// Part of `balance` already owed elsewhere (pending withdrawals)
+    pub reserved: u64,
+}
+
+/// Outcome of `compute_distributable`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Distributable {
+    /// Final investor quote of the page, carry included
+    pub investor_fee_quote: u64,
+    /// Carry taken into `investor_fee_quote`
+    pub carry_fed: u64,
+    /// Part of the claim not going to investors
+    pub creator_base: u64,
+    /// The lifetime cap cut the quote down
+    pub lifetime_capped: bool,
+    /// Quote before the balance clamp, when the clamp cut it down
+    pub clamped_from: Option<u64>,
+}
+
+/// Investor quote of a page, in this order:
+/// 1. share of `claimed`, tiered and scaled by f_locked;
+/// 2. daily cap;
+/// 3. carry, within what is left of the daily and lifetime caps;
+/// 4. lifetime cap;
+/// 5. reserve: `reserved` comes off `balance`;
+/// 6. clamp to what is left of the balance.
+///
+/// An exhausted daily or lifetime cap yields nothing, without flagging a cut.
+pub fn compute_distributable(claimed: u64, caps: &PageCaps) -> Result<Distributable, MathError> {
+    if caps.daily_cap_left == Some(0) || caps.lifetime_cap_left == Some(0) {
+        return Ok(Distributable { creator_base: claimed, ..Default::default() });
+    }
+    let mut quote = investor_fee_quote(claimed, caps.y0, caps.share_tiers, caps.daily_cap_left, 0, caps.locked_total)?;
+
+    let carry_fed = if caps.carry > 0 && caps.locked_total > 0 {
+        let room = caps.daily_cap_left.map_or(u64::MAX, |left| left.saturating_sub(quote));
+        let lifetime_room = caps
+            .lifetime_cap_left
+            .map_or(u64::MAX, |left| u64::try_from(left.saturating_sub(quote as u128)).unwrap_or(u64::MAX));
+        caps.carry.min(room).min(lifetime_room)
+    } else {
+        0
+    };
+    quote = quote.checked_add(carry_fed).ok_or(MathError::Overflow)?;
+
+    let mut lifetime_capped = false;
+    if let Some(left) = caps.lifetime_cap_left {
+        if quote as u128 > left {
+            quote = left as u64;
+            lifetime_capped = true;
+        }
+    }
+
+    let available = caps.balance.saturating_sub(caps.reserved);
+    let clamped_from = (quote > available).then_some(quote);
+    quote = quote.min(available);
+
+    // Cuts come off the claim's part first; the claim's share never exceeds the claim
+    let claim_part = quote.saturating_sub(carry_fed);
+    Ok(Distributable {
+        investor_fee_quote: quote,
+        carry_fed,
+        creator_base: claimed - claim_part,
+        lifetime_capped,
+        clamped_from,
+    })
+}
+
+/// Whether a claim's reported amount is within `tolerance_bps` of the observed treasury
+/// delta (of the larger of the two). A report in another unit is off by orders of magnitude.
+pub fn claim_within_tolerance(reported: u64, observed: u64, tolerance_bps: u16) -> bool {
+    let allowed = (reported.max(observed) as u128) * (tolerance_bps as u128) / BPS_DENOMINATOR;
+    (reported.abs_diff(observed) as u128) <= allowed
+}
+
+/// `quote_amount` in payout-mint units at a fixed `conversion_rate`, floored
+pub fn convert_at_rate(quote_amount: u64, conversion_rate: u64) -> Result<u64, MathError> {
+    u64::try_from((quote_amount as u128) * (conversion_rate as u128) / CONVERSION_RATE_SCALE).map_err(|_| MathError::Overflow)
+}
+
+/// `tokens` whole tokens in raw units of a mint with `decimals`
+pub fn scale_whole_tokens(tokens: u64, decimals: u8) -> Result<u64, MathError> {
+    10u64
+        .checked_pow(decimals as u32)
+        .and_then(|unit| tokens.checked_mul(unit))
+        .ok_or(MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const Y0: u128 = 1_000_000;
+
+    fn distribute(claimed: u64, cap: Option<u64>, cumulative: u64, min_payout: u64, locked: &[u64]) -> DistributionResult {
+        compute_distribution(claimed, Y0, &[(0, 5_000)], cap, cumulative, min_payout, locked).unwrap()
+    }
+
+    #[test]
+    fn linear_vested_over_window() {
+        assert_eq!(linear_vested(1_000, 100, 200, 50), 0);
+        assert_eq!(linear_vested(1_000, 100, 200, 100), 0);
+        assert_eq!(linear_vested(1_000, 100, 200, 125), 250);
+        assert_eq!(linear_vested(1_000, 100, 200, 199), 990);
+        assert_eq!(linear_vested(1_000, 100, 200, 200), 1_000);
+        assert_eq!(linear_vested(1_000, 100, 200, 10_000), 1_000);
+        // Empty window vests immediately
+        assert_eq!(linear_vested(1_000, 100, 100, 100), 1_000);
+        assert_eq!(linear_vested(u64::MAX, 0, 4, 2), u64::MAX / 2);
+    }
+
+    #[test]
+    fn cumulative_near_u64_max() {
+        assert_eq!(add_distributed(u64::MAX - 5, 5), Ok(u64::MAX));
+        assert_eq!(add_distributed(u64::MAX - 5, 6), Err(MathError::Overflow));
+        assert_eq!(add_distributed(u64::MAX, 0), Ok(u64::MAX));
+        // The cap room left by a cumulative that close to a u64::MAX cap still holds
+        let r = distribute(u64::MAX, Some(u64::MAX), u64::MAX - 10, 0, &[1_000_000]);
+        assert_eq!(r.investor_fee_quote, 10);
+        assert_eq!(add_distributed(u64::MAX - 10, r.total_payout), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn conversion_at_a_fixed_rate() {
+        // 2.5 payout units per quote unit
+        assert_eq!(convert_at_rate(40_000, 2_500_000_000), Ok(100_000));
+        // Floored: 3 quote units at 0.5 is 1
+        assert_eq!(convert_at_rate(3, 500_000_000), Ok(1));
+        assert_eq!(convert_at_rate(u64::MAX, 1_000_000_000), Ok(u64::MAX));
+        assert_eq!(convert_at_rate(u64::MAX, 2_000_000_000), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn share_above_bps() {
+        assert!(share_exceeds_bps(9, 10, 8_000));
+        assert!(!share_exceeds_bps(8, 10, 8_000));
+        assert!(!share_exceeds_bps(0, 0, 0));
+        assert!(share_exceeds_bps(1, 3, 0));
+        assert!(!share_exceeds_bps(u64::MAX, u64::MAX, 10_000));
+    }
+
+    #[test]
+    fn whole_tokens_scale_by_decimals() {
+        assert_eq!(scale_whole_tokens(3, 6), Ok(3_000_000));
+        assert_eq!(scale_whole_tokens(7, 0), Ok(7));
+        assert_eq!(scale_whole_tokens(0, 9), Ok(0));
+        assert_eq!(scale_whole_tokens(u64::MAX / 1_000, 3), Ok(u64::MAX / 1_000 * 1_000));
+        assert_eq!(scale_whole_tokens(20_000_000_000, 9), Err(MathError::Overflow));
+        assert_eq!(scale_whole_tokens(1, 20), Err(MathError::Overflow));
+    }
+
+    // 40% locked at a 50% share: the claim's investor quote is 40%
+    fn page_caps(daily_cap_left: Option<u64>, lifetime_cap_left: Option<u128>, carry: u64, balance: u64, reserved: u64) -> PageCaps<'static> {
+        PageCaps {
+            y0: Y0,
+            share_tiers: &[(0, 5_000)],
+            locked_total: 400_000,
+            daily_cap_left,
+            lifetime_cap_left,
+            carry,
+            balance,
+            reserved,
+        }
+    }
+
+    #[test]
+    fn distributable_without_caps_is_the_share() {
+        let d = compute_distributable(100_000, &page_caps(None, None, 0, u64::MAX, 0)).unwrap();
+        assert_eq!(d, Distributable { investor_fee_quote: 40_000, creator_base: 60_000, ..Default::default() });
+    }
+
+    #[test]
+    fn daily_cap_bounds_share_and_carry() {
+        let d = compute_distributable(100_000, &page_caps(Some(30_000), None, 5_000, u64::MAX, 0)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.carry_fed, d.creator_base), (30_000, 0, 70_000));
+        // Room left under the cap takes part of the carry
+        let d = compute_distributable(100_000, &page_caps(Some(43_000), None, 5_000, u64::MAX, 0)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.carry_fed, d.creator_base), (43_000, 3_000, 60_000));
+    }
+
+    #[test]
+    fn lifetime_cap_applies_after_the_carry() {
+        let d = compute_distributable(100_000, &page_caps(None, Some(42_000), 5_000, u64::MAX, 0)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.carry_fed), (42_000, 2_000));
+        assert!(!d.lifetime_capped);
+        // The claim's share alone is over the lifetime room: no carry, and the cut is flagged
+        let d = compute_distributable(100_000, &page_caps(None, Some(25_000), 5_000, u64::MAX, 0)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.carry_fed, d.creator_base), (25_000, 0, 75_000));
+        assert!(d.lifetime_capped);
+        // The tighter of the daily and lifetime caps wins
+        let d = compute_distributable(100_000, &page_caps(Some(20_000), Some(25_000), 0, u64::MAX, 0)).unwrap();
+        assert_eq!(d.investor_fee_quote, 20_000);
+        assert!(!d.lifetime_capped);
+    }
+
+    #[test]
+    fn reserve_and_balance_clamp_come_last() {
+        // 50k in the treasury, 15k of it reserved for pending withdrawals
+        let d = compute_distributable(100_000, &page_caps(Some(45_000), None, 0, 50_000, 15_000)).unwrap();
+        assert_eq!(d.investor_fee_quote, 35_000);
+        assert_eq!(d.clamped_from, Some(40_000));
+        assert_eq!(d.creator_base, 65_000);
+        // Clamped below a lifetime-capped quote: both cuts are reported
+        let d = compute_distributable(100_000, &page_caps(None, Some(30_000), 0, 20_000, 0)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.clamped_from), (20_000, Some(30_000)));
+        assert!(d.lifetime_capped);
+        // A reserve above the balance leaves nothing to pay
+        let d = compute_distributable(100_000, &page_caps(None, None, 0, 10_000, 12_000)).unwrap();
+        assert_eq!((d.investor_fee_quote, d.creator_base), (0, 100_000));
+    }
+
+    #[test]
+    fn exhausted_caps_distribute_nothing() {
+        for caps in [page_caps(Some(0), None, 5_000, u64::MAX, 0), page_caps(None, Some(0), 5_000, u64::MAX, 0)] {
+            let d = compute_distributable(100_000, &caps).unwrap();
+            assert_eq!(d, Distributable { creator_base: 100_000, ..Default::default() });
+        }
+    }
+
+    #[test]
+    fn zero_locked_pays_nothing() {
+        let r = distribute(100_000, None, 0, 0, &[0, 0]);
+        assert_eq!(r.investor_fee_quote, 0);
+        assert_eq!(r.payouts, vec![0, 0]);
+        assert_eq!((r.total_payout, r.dust), (0, 0));
+    }
+
+    #[test]
+    fn no_investors_pays_nothing() {
+        let r = distribute(100_000, None, 0, 0, &[]);
+        assert_eq!(r, DistributionResult::default());
+    }
+
+    #[test]
+    fn partial_lock_scales_share_by_f_locked() {
+        // 20% locked < 50% share
+        let r = distribute(100_000, None, 0, 0, &[150_000, 50_000]);
+        assert_eq!(r.investor_fee_quote, 20_000);
+        assert_eq!(r.payouts, vec![15_000, 5_000]);
+        assert_eq!(r.dust, 0);
+    }
+
+    #[test]
+    fn full_lock_is_capped_at_investor_share() {
+        let r = distribute(100_000, None, 0, 0, &[600_000, 400_000]);
+        assert_eq!(r.investor_fee_quote, 50_000);
+        assert_eq!(r.payouts, vec![30_000, 20_000]);
+    }
+
+    #[test]
+    fn locked_above_y0_saturates_f_locked() {
+        let r = distribute(100_000, None, 0, 0, &[5_000_000]);
+        assert_eq!(r.investor_fee_quote, 50_000);
+    }
+
+    #[test]
+    fn zero_y0_pays_nothing() {
+        let r = compute_distribution(100_000, 0, &[(0, 5_000)], None, 0, 0, &[1_000]).unwrap();
+        assert_eq!(r.investor_fee_quote, 0);
+        assert_eq!(r.payouts, vec![0]);
+    }
+
+    #[test]
+    fn daily_cap_limits_investor_fee_quote() {
+        let r = distribute(100_000, Some(30_000), 10_000, 0, &[1_000_000]);
+        assert_eq!(r.investor_fee_quote, 20_000);
+        assert_eq!(r.total_payout, 20_000);
+    }
+
+    #[test]
+    fn exhausted_daily_cap_pays_nothing() {
+        let r = distribute(100_000, Some(30_000), 30_000, 0, &[1_000_000]);
+        assert_eq!(r.investor_fee_quote, 0);
+        assert_eq!(r.payouts, vec![0]);
+    }
+
+    #[test]
+    fn shares_below_min_payout_become_dust() {
+        // 20_000 split 19_900 / 100; only the small share is dusted
+        let r = distribute(100_000, None, 0, 1_000, &[199_000, 1_000]);
+        assert_eq!(r.shares, vec![19_900, 100]);
+        assert_eq!(r.payouts, vec![19_900, 0]);
+        assert_eq!(r.dust, 100);
+    }
+
+    #[test]
+    fn all_dust_when_every_share_is_below_min_payout() {
+        let r = distribute(1_000, None, 0, 1_000, &[500_000, 500_000]);
+        assert_eq!(r.investor_fee_quote, 500);
+        assert_eq!(r.payouts, vec![0, 0]);
+        assert_eq!((r.total_payout, r.dust), (0, 500));
+    }
+
+    #[test]
+    fn rounding_leftover_is_dust() {
+        // 3 equal locks: 100 -> 33 each, 1 left over
+        let r = compute_distribution(200, Y0, &[(0, 5_000)], None, 0, 0, &[400_000, 400_000, 400_000]).unwrap();
+        assert_eq!(r.investor_fee_quote, 100);
+        assert_eq!(r.payouts, vec![33, 33, 33]);
+        assert_eq!(r.dust, 1);
+    }
+
+    #[test]
+    fn apportion_splits_a_fixed_amount_without_f_locked() {
+        let r = apportion(1_001, 100, &[750_000, 250_000, 50]).unwrap();
+        assert_eq!(r.payouts, vec![750, 250, 0]);
+        assert_eq!(r.dust, 1);
+        // Nothing locked: the whole amount stays dust
+        let r = apportion(500, 0, &[0, 0]).unwrap();
+        assert_eq!((r.total_payout, r.dust), (0, 500));
+    }
+
+    #[test]
+    fn rounding_leftover_goes_to_largest_remainders_or_one_investor() {
+        let weights = [100_000, 200_000, 400_000];
+        let floored = apportion(50_000, 0, &weights).unwrap();
+        assert_eq!(floored.shares, vec![7_142, 14_285, 28_571]);
+        assert_eq!(rounding_leftover(&floored), 2);
+
+        // Remainders are 6/7, 5/7 and 3/7 of a unit
+        let mut r = floored.clone();
+        assign_largest_remainders(&mut r, &weights, 0).unwrap();
+        assert_eq!(r.payouts, vec![7_143, 14_286, 28_571]);
+        assert_eq!((r.total_payout, r.dust), (50_000, 0));
+
+        let mut r = floored;
+        assign_leftover_to(&mut r, 2, 0).unwrap();
+        assert_eq!(r.payouts, vec![7_142, 14_285, 28_573]);
+        assert_eq!(r.dust, 0);
+
+        // A dusted share keeps its dust
+        let mut r = apportion(50_000, 10_000, &weights).unwrap();
+        assign_leftover_to(&mut r, 2, 10_000).unwrap();
+        assert_eq!(r.payouts, vec![0, 14_285, 28_573]);
+        assert_eq!(r.dust, 7_142);
+    }
+
+    #[test]
+    fn extreme_values_do_not_overflow() {
+        let r = compute_distribution(u64::MAX, u128::MAX, &[(0, 10_000)], None, 0, 0, &[u64::MAX, u64::MAX]).unwrap();
+        assert_eq!(r.total_payout + r.dust, r.investor_fee_quote);
+
+        let r = compute_distribution(u64::MAX, 1, &[(0, 10_000)], None, 0, 0, &[u64::MAX, u64::MAX, 1]).unwrap();
+        assert_eq!(r.investor_fee_quote, u64::MAX);
+        assert_eq!(r.total_payout + r.dust, u64::MAX);
+        assert!(r.payouts.iter().all(|p| *p <= u64::MAX / 2));
+    }
+
+    #[test]
+    fn claim_tolerance_catches_unit_mismatches() {
+        assert!(claim_within_tolerance(100_000, 100_000, 0));
+        assert!(!claim_within_tolerance(100_001, 100_000, 0));
+        // 1% of the larger amount either way
+        assert!(claim_within_tolerance(101_000, 100_000, 100));
+        assert!(claim_within_tolerance(100_000, 101_000, 100));
+        assert!(!claim_within_tolerance(102_000, 100_000, 100));
+        // Reported in 10^-3 units of what moved, or the other way round
+        assert!(!claim_within_tolerance(100_000_000, 100_000, 5_000));
+        assert!(!claim_within_tolerance(100, 100_000, 5_000));
+        assert!(claim_within_tolerance(u64::MAX, u64::MAX - 1, 1));
+    }
+
+    // 80% on the first 10k claimed, 50% above
+    const TIERS: [(u64, u16); 2] = [(0, 8_000), (10_000, 5_000)];
+
+    #[test]
+    fn tiers_apply_progressively_across_boundaries() {
+        let full_lock = [Y0 as u64];
+        let quote = |claimed| compute_distribution(claimed, Y0, &TIERS, None, 0, 0, &full_lock).unwrap().investor_fee_quote;
+        assert_eq!(quote(9_999), 7_999);
+        assert_eq!(quote(10_000), 8_000);
+        assert_eq!(quote(10_001), 8_000);
+        assert_eq!(quote(10_002), 8_001);
+        assert_eq!(quote(30_000), 8_000 + 10_000);
+    }
+
+    #[test]
+    fn f_locked_caps_each_tier() {
+        // 60% locked: the 80% tier is capped at 60%, the 50% tier is not
+        let r = compute_distribution(30_000, Y0, &TIERS, None, 0, 0, &[600_000]).unwrap();
+        assert_eq!(r.investor_fee_quote, 6_000 + 10_000);
+    }
+
+    #[test]
+    fn eligible_share_is_capped_by_f_locked() {
+        assert_eq!(eligible_bps(5_000, f_locked_bps(400_000, Y0)), 4_000);
+        assert_eq!(eligible_bps(5_000, f_locked_bps(700_000, Y0)), 5_000);
+        assert_eq!(eligible_bps(10_000, f_locked_bps(u128::MAX, Y0)), 10_000);
+        assert_eq!(eligible_bps(5_000, f_locked_bps(1, 0)), 0);
+    }
+
+    #[test]
+    fn single_tier_matches_flat_share() {
+        for claimed in [0, 1, 9_999, 10_000, 123_457] {
+            let flat = distribute(claimed, None, 0, 0, &[150_000, 50_000]);
+            let tiered = compute_distribution(claimed, Y0, &[(0, 5_000)], None, 0, 0, &[150_000, 50_000]).unwrap();
+            assert_eq!(flat, tiered);
+            assert_eq!(flat.investor_fee_quote, (claimed as u128 * 2_000 / 10_000) as u64);
+        }
+    }
+
+    #[test]
+    fn bps_of_floors() {
+        assert_eq!(bps_of(100_000, 2_000), Ok(20_000));
+        assert_eq!(bps_of(9, 5_000), Ok(4));
+        assert_eq!(bps_of(u64::MAX, 10_000), Ok(u64::MAX));
+    }
+
+    proptest! {
+        #[test]
+        fn payouts_plus_dust_equal_investor_fee_quote(
+            claimed in any::<u64>(),
+            y0 in 1u128..=u64::MAX as u128,
+            share_bps in 0u16..=10_000,
+            cap in proptest::option::of(any::<u64>()),
+            cumulative in any::<u64>(),
+            min_payout in 0u64..1_000_000,
+            locked in proptest::collection::vec(any::<u64>(), 0..20),
+        ) {
+            let r = compute_distribution(claimed, y0, &[(0, share_bps)], cap, cumulative, min_payout, &locked).unwrap();
+            let paid: u128 = r.payouts.iter().map(|p| *p as u128).sum();
+            prop_assert_eq!(paid, r.total_payout as u128);
+            prop_assert_eq!(r.total_payout as u128 + r.dust as u128, r.investor_fee_quote as u128);
+        }
+
+        #[test]
+        fn single_investor_path_matches_pro_rata(
+            investor_fee_quote in any::<u64>(),
+            min_payout in 0u64..1_000_000,
+            locked in prop_oneof![Just(0u64), any::<u64>()],
+        ) {
+            let fast = apportion(investor_fee_quote, min_payout, &[locked]).unwrap();
+            let general = apportion_pro_rata(investor_fee_quote, min_payout, &[locked]).unwrap();
+            prop_assert_eq!(fast.shares, general.shares);
+            prop_assert_eq!(fast.payouts, general.payouts);
+            prop_assert_eq!((fast.total_payout, fast.dust), (general.total_payout, general.dust));
+        }
+
+        #[test]
+        fn no_investor_exceeds_proportional_share(
+            claimed in any::<u64>(),
+            y0 in 1u128..=u64::MAX as u128,
+            share_bps in 0u16..=10_000,
+            min_payout in 0u64..1_000_000,
+            locked in proptest::collection::vec(any::<u64>(), 1..20),
+        ) {
+            let r = compute_distribution(claimed, y0, &[(0, share_bps)], None, 0, min_payout, &locked).unwrap();
+            let locked_total: u128 = locked.iter().map(|l| *l as u128).sum();
+            for (i, payout) in r.payouts.iter().enumerate() {
+                // payout * total <= ifq * locked  <=>  payout <= ifq * locked / total
+                prop_assert!((*payout as u128) * locked_total <= (r.investor_fee_quote as u128) * (locked[i] as u128));
+                prop_assert!(*payout == 0 || *payout >= min_payout);
+                prop_assert!(*payout == 0 || *payout == r.shares[i]);
+            }
+        }
+
+        #[test]
+        fn investor_fee_quote_respects_share_and_cap(
+            claimed in any::<u64>(),
+            y0 in 1u128..=u64::MAX as u128,
+            share_bps in 0u16..=10_000,
+            cap in proptest::option::of(any::<u64>()),
+            cumulative in any::<u64>(),
+            locked_total in any::<u64>(),
+        ) {
+            let ifq = investor_fee_quote(claimed, y0, &[(0, share_bps)], cap, cumulative, locked_total as u128).unwrap();
+            prop_assert!(ifq as u128 <= (claimed as u128) * (share_bps as u128) / BPS_DENOMINATOR);
+            if let Some(cap) = cap {
+                prop_assert!(ifq <= cap.saturating_sub(cumulative));
+            }
+        }
+
+        #[test]
+        fn tiered_quote_stays_between_lowest_and_highest_tier(
+            claimed in any::<u64>(),
+            breakpoint in any::<u64>(),
+            low_bps in 0u16..=10_000,
+            high_bps in 0u16..=10_000,
+        ) {
+            let tiers = [(0, high_bps), (breakpoint, low_bps)];
+            let tiered = investor_fee_quote(claimed, Y0, &tiers, None, 0, Y0).unwrap();
+            let flat = |bps| investor_fee_quote(claimed, Y0, &[(0, bps)], None, 0, Y0).unwrap();
+            prop_assert!(tiered >= flat(low_bps.min(high_bps)));
+            prop_assert!(tiered <= flat(low_bps.max(high_bps)));
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Day gate and page cursor state machine of `crank_distribute`, on plain integers.
+//!
+//! `crank_distribute` copies the relevant `Progress` fields into a [`DayState`], runs
+//! [`check_page`] / [`start_day`] on it and writes it back, so the gating rules can be
+//! fuzzed without an Anchor context.
+
+pub const DAY_SECONDS: i64 = 86_400;
+pub const DAY_LIMIT_PERIOD_SECONDS: i64 = 30 * DAY_SECONDS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateError {
+    DayWindowNotElapsed,
+    InvalidCursor,
+    DayLimitReached,
+    PageOutOfRange,
+    InitialDelayNotElapsed,
+}
+
+/// Day/cursor fields of `Progress`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DayState {
+    pub day_start_ts: i64,
+    pub last_distribution_ts: i64,
+    pub cursor: u64,
+    pub period_start_ts: i64,
+    pub days_in_period: u16,
+}
+
+/// Policy knobs the gate depends on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DayConfig {
+    pub align_to_utc_midnight: bool,
+    /// 0 = unlimited
+    pub max_days_per_period: u16,
+    /// Pages every day has; higher page indexes are rejected (0 = unchecked)
+    pub total_pages_expected: u32,
+    /// Earliest time the first-ever day may start (0 = no delay)
+    pub first_day_not_before: i64,
+}
+
+/// What a crank call for `page_index` does before any claim or payout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageGate {
+    /// Page 0 retry within the open day: replay without touching the day
+    ReplayOpenDay,
+    /// Page 0 opening a new day: call [`start_day`]
+    StartDay,
+    /// Page of the open day (the page itself may still be a replay)
+    Continue,
+}
+
+impl DayState {
+    /// A day was started and its last page has not run yet
+    pub fn day_in_progress(&self) -> bool {
+        self.day_start_ts > self.last_distribution_ts
+    }
+
+    /// Earliest time page 0 may open the next day
+    pub fn next_day_allowed_ts(&self, config: &DayConfig) -> i64 {
+        // Aligned days open at the next boundary after the previous day start,
+        // unaligned days open a full interval after the previous close
+        if config.align_to_utc_midnight {
+            self.day_start_ts + DAY_SECONDS
+        } else {
+            self.last_distribution_ts + DAY_SECONDS
+        }
+    }
+
+    /// Earliest time page 0 may start a day, the first-ever one included
+    pub fn page0_allowed_ts(&self, config: &DayConfig) -> i64 {
+        if self.last_distribution_ts == 0 {
+            config.first_day_not_before
+        } else {
+            self.next_day_allowed_ts(config)
+        }
+    }
+
+    /// Earliest time page 0 can open a new day: [`Self::page0_allowed_ts`], but not within
+    /// the open day's window, and not before the period ends once its day starts are used up
+    pub fn next_day_start_ts(&self, config: &DayConfig) -> i64 {
+        let mut ts = self.page0_allowed_ts(config);
+        if self.day_in_progress() {
+            ts = ts.max(self.day_start_ts + DAY_SECONDS);
+        }
+        if config.max_days_per_period != 0 && self.days_in_period >= config.max_days_per_period {
+            ts = ts.max(self.period_start_ts + DAY_LIMIT_PERIOD_SECONDS);
+        }
+        ts
+    }
+}
+
+/// Start of the UTC day containing `ts`
+pub fn day_boundary(ts: i64) -> i64 {
+    ts - ts.rem_euclid(DAY_SECONDS)
+}
+
+/// Day gate and cursor rules for a crank call; `page0_recorded` is whether today's page
+/// records already contain page 0
+pub fn check_page(
+    state: &DayState,
+    config: &DayConfig,
+    page_index: u64,
+    now_ts: i64,
+    page0_recorded: bool,
+) -> Result<PageGate, GateError> {
+    if config.total_pages_expected != 0 && page_index >= config.total_pages_expected as u64 {
+        return Err(GateError::PageOutOfRange);
+    }
+    if page_index == 0 {
+        // Page 0 of a day that is still open is a retry, not a new day. Once the window
+        // has passed, page 0 starts a new day.
+        if state.day_in_progress() && now_ts < state.day_start_ts + DAY_SECONDS && page0_recorded {
+            return Ok(PageGate::ReplayOpenDay);
+        }
+        if state.last_distribution_ts != 0 && now_ts < state.next_day_allowed_ts(config) {
+            return Err(GateError::DayWindowNotElapsed);
+        }
+        // Gives fees time to accrue before anything is distributed
+        if state.last_distribution_ts == 0 && now_ts < config.first_day_not_before {
+            return Err(GateError::InitialDelayNotElapsed);
+        }
+        return Ok(PageGate::StartDay);
+    }
+    // subsequent pages must be within same day
+    if now_ts >= state.day_start_ts + DAY_SECONDS {
+        return Err(GateError::DayWindowNotElapsed);
+    }
+    // allow retry of current page or advancing by one
+    if page_index != state.cursor && page_index != state.cursor + 1 {
+        return Err(GateError::InvalidCursor);
+    }
+    Ok(PageGate::Continue)
+}
+
+/// Open a new day at `now_ts`, enforcing the day-start limit per rolling 30-day period
+pub fn start_day(state: &mut DayState, config: &DayConfig, now_ts: i64) -> Result<(), GateError> {
+    let mut next = *state;
+    if now_ts >= next.period_start_ts + DAY_LIMIT_PERIOD_SECONDS {
+        next.period_start_ts = now_ts;
+        next.days_in_period = 0;
+    }
+    let max_days = config.max_days_per_period;
+    if max_days != 0 && next.days_in_period >= max_days {
+        return Err(GateError::DayLimitReached);
+    }
+    next.days_in_period = next.days_in_period.saturating_add(1);
+    next.day_start_ts = if config.align_to_utc_midnight {
+        day_boundary(now_ts)
+    } else {
+        now_ts
+    };
+    next.cursor = 0;
+    *state = next;
+    Ok(())
+}
+
+/// Share of a day's `total_pages` done, in bps (`None` when no page count is committed)
+pub fn completion_bps(pages_done: u32, total_pages: u32) -> Option<u16> {
+    if total_pages == 0 {
+        return None;
+    }
+    Some((pages_done.min(total_pages) as u64 * 10_000 / total_pages as u64) as u16)
+}
+
+/// Cursor after `page_index` was processed (distributed or replayed); never moves back
+pub fn advance_cursor(cursor: u64, page_index: u64) -> u64 {
+    cursor.max(page_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    const Y0: u128 = 1_000_000;
+    const LOCKS: [u64; 3] = [300_000, 200_000, 7];
+
+    /// One `crank_distribute` call in a generated sequence
+    #[derive(Clone, Debug)]
+    struct Call {
+        advance_secs: i64,
+        page_index: u64,
+        is_last_page: bool,
+        /// Repeat the same call right away, as a keeper retry would
+        retry: bool,
+        /// Fees accrued on the position before the call
+        accrued: u64,
+    }
+
+    fn call() -> impl Strategy<Value = Call> {
+        (
+            prop_oneof![Just(0i64), 1i64..3_600, 80_000i64..100_000, 0i64..3 * DAY_SECONDS],
+            0u64..5,
+            any::<bool>(),
+            any::<bool>(),
+            0u64..1_000_000,
+        )
+            .prop_map(|(advance_secs, page_index, is_last_page, retry, accrued)| Call {
+                advance_secs,
+                page_index,
+                is_last_page,
+                retry,
+                accrued,
+            })
+    }
+
+    /// Crank model: the real gate plus `math::compute_distribution` over a token ledger
+    #[derive(Clone, Debug, Default)]
+    struct Model {
+        config: DayConfig,
+        day: DayState,
+        now_ts: i64,
+        page_records: Vec<u64>,
+        cumulative_distributed_today: u64,
+        claimed_today: u64,
+        carry: u64,
+        // ledger
+        unclaimed: u64,
+        treasury: u64,
+        paid_to_investors: u64,
+        paid_to_creator: u64,
+        accrued_total: u64,
+        // history for invariants
+        distributed: BTreeSet<(i64, u64)>,
+        day_starts: Vec<i64>,
+    }
+
+    impl Model {
+        fn new(config: DayConfig, start_ts: i64) -> Self {
+            Model { config, now_ts: start_ts, ..Default::default() }
+        }
+
+        /// Returns whether the call succeeded; a failed call reverts like a transaction
+        fn crank(&mut self, page_index: u64, is_last_page: bool) -> bool {
+            let before = self.clone();
+            let ok = self.try_crank(page_index, is_last_page).is_ok();
+            if !ok {
+                *self = before;
+            }
+            ok
+        }
+
+        fn try_crank(&mut self, page_index: u64, is_last_page: bool) -> Result<(), GateError> {
+            let page0_recorded = self.page_records.contains(&0);
+            match check_page(&self.day, &self.config, page_index, self.now_ts, page0_recorded)? {
+                PageGate::ReplayOpenDay => return Ok(()),
+                PageGate::StartDay => {
+                    start_day(&mut self.day, &self.config, self.now_ts)?;
+                    self.page_records.clear();
+                    self.cumulative_distributed_today = 0;
+                    self.claimed_today = 0;
+                    self.day_starts.push(self.day.day_start_ts);
+                }
+                PageGate::Continue => {}
+            }
+            if self.page_records.contains(&page_index) {
+                self.day.cursor = advance_cursor(self.day.cursor, page_index);
+                return Ok(());
+            }
+
+            let claimed = core::mem::take(&mut self.unclaimed);
+            self.treasury += claimed;
+            let r = math::compute_distribution(
+                claimed,
+                Y0,
+                &[(0, 5_000)],
+                Some(400_000),
+                self.cumulative_distributed_today,
+                1_000,
+                &LOCKS,
+            )
+            .unwrap();
+            self.treasury -= r.total_payout;
+            self.paid_to_investors += r.total_payout;
+            self.carry += r.dust;
+            self.cumulative_distributed_today += r.total_payout;
+            self.claimed_today += claimed;
+            self.page_records.push(page_index);
+            self.day.cursor = advance_cursor(self.day.cursor, page_index);
+            assert!(
+                self.distributed.insert((self.day.day_start_ts, page_index)),
+                "page {page_index} of day {} distributed twice",
+                self.day.day_start_ts
+            );
+
+            if is_last_page {
+                let remainder = (self.claimed_today - self.cumulative_distributed_today + self.carry).min(self.treasury);
+                self.treasury -= remainder;
+                self.paid_to_creator += remainder;
+                self.carry = 0;
+                self.day.last_distribution_ts = self.now_ts;
+            }
+            Ok(())
+        }
+    }
+
+    fn run(config: DayConfig, start_ts: i64, calls: &[Call]) -> Result<(), TestCaseError> {
+        let mut m = Model::new(config, start_ts);
+        for c in calls {
+            m.now_ts += c.advance_secs;
+            m.unclaimed += c.accrued;
+            m.accrued_total += c.accrued;
+            let attempts = if c.retry { 2 } else { 1 };
+            for _ in 0..attempts {
+                let prev = m.clone();
+                let ok = m.crank(c.page_index, c.is_last_page);
+
+                // Cursor only moves back when a new day starts
+                if m.day.day_start_ts == prev.day.day_start_ts {
+                    prop_assert!(m.day.cursor >= prev.day.cursor);
+                } else {
+                    prop_assert!(ok && c.page_index == 0);
+                    prop_assert!(m.day.cursor == 0);
+                }
+                // A retry changes nothing but the cursor
+                if ok && prev.page_records.contains(&c.page_index) && m.day.day_start_ts == prev.day.day_start_ts {
+                    prop_assert_eq!(m.treasury, prev.treasury);
+                    prop_assert_eq!(m.paid_to_investors, prev.paid_to_investors);
+                    prop_assert_eq!(m.paid_to_creator, prev.paid_to_creator);
+                    prop_assert_eq!(&m.page_records, &prev.page_records);
+                }
+                // No funds created or destroyed, and carry is always backed by the treasury
+                prop_assert_eq!(
+                    m.unclaimed as u128 + m.treasury as u128 + m.paid_to_investors as u128 + m.paid_to_creator as u128,
+                    m.accrued_total as u128
+                );
+                prop_assert!(m.carry <= m.treasury);
+                prop_assert!(m.cumulative_distributed_today <= 400_000);
+                prop_assert!(config.max_days_per_period == 0 || m.day.days_in_period <= config.max_days_per_period);
+            }
+        }
+        // Day gate: consecutive day starts respect the 24h window
+        for pair in m.day_starts.windows(2) {
+            prop_assert!(pair[1] - pair[0] >= DAY_SECONDS);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn page_zero_retry_replays_open_day() {
+        let config = DayConfig::default();
+        let mut day = DayState::default();
+        assert_eq!(check_page(&day, &config, 0, 1_000, false), Ok(PageGate::StartDay));
+        start_day(&mut day, &config, 1_000).unwrap();
+        assert_eq!(check_page(&day, &config, 0, 2_000, true), Ok(PageGate::ReplayOpenDay));
+        // After the window page 0 opens a new day, even if the last page never ran
+        assert_eq!(check_page(&day, &config, 0, 1_000 + DAY_SECONDS, true), Ok(PageGate::StartDay));
+    }
+
+    #[test]
+    fn later_pages_require_open_day_and_adjacent_cursor() {
+        let config = DayConfig::default();
+        let mut day = DayState::default();
+        start_day(&mut day, &config, 1_000).unwrap();
+        assert_eq!(check_page(&day, &config, 1, 1_001, true), Ok(PageGate::Continue));
+        assert_eq!(check_page(&day, &config, 2, 1_001, true), Err(GateError::InvalidCursor));
+        day.cursor = 2;
+        assert_eq!(check_page(&day, &config, 2, 1_001, true), Ok(PageGate::Continue));
+        assert_eq!(check_page(&day, &config, 3, 1_001, true), Ok(PageGate::Continue));
+        assert_eq!(check_page(&day, &config, 1, 1_001, true), Err(GateError::InvalidCursor));
+        assert_eq!(check_page(&day, &config, 3, 1_000 + DAY_SECONDS, true), Err(GateError::DayWindowNotElapsed));
+    }
+
+    #[test]
+    fn pages_beyond_the_committed_total_are_out_of_range() {
+        let config = DayConfig { total_pages_expected: 3, ..Default::default() };
+        let mut day = DayState::default();
+        start_day(&mut day, &config, 1_000).unwrap();
+        day.cursor = 2;
+        assert_eq!(check_page(&day, &config, 2, 1_001, true), Ok(PageGate::Continue));
+        assert_eq!(check_page(&day, &config, 3, 1_001, true), Err(GateError::PageOutOfRange));
+        // Checked before the cursor, so a far-off index reports the range, not the cursor
+        assert_eq!(check_page(&day, &config, 9, 1_001, true), Err(GateError::PageOutOfRange));
+    }
+
+    #[test]
+    fn first_day_waits_for_the_initial_delay() {
+        let config = DayConfig { first_day_not_before: 5_000, ..Default::default() };
+        let mut day = DayState::default();
+        assert_eq!(check_page(&day, &config, 0, 4_999, false), Err(GateError::InitialDelayNotElapsed));
+        assert_eq!(check_page(&day, &config, 0, 5_000, false), Ok(PageGate::StartDay));
+        assert_eq!(day.page0_allowed_ts(&config), 5_000);
+        // Only the first day is held back
+        start_day(&mut day, &config, 5_000).unwrap();
+        day.last_distribution_ts = 5_100;
+        assert_eq!(day.page0_allowed_ts(&config), 5_100 + DAY_SECONDS);
+        assert_eq!(check_page(&day, &config, 0, 5_100 + DAY_SECONDS, true), Ok(PageGate::StartDay));
+    }
+
+    #[test]
+    fn next_day_waits_for_window() {
+        let mut day = DayState::default();
+        let unaligned = DayConfig::default();
+        start_day(&mut day, &unaligned, 1_000).unwrap();
+        day.last_distribution_ts = 5_000;
+        assert_eq!(check_page(&day, &unaligned, 0, 4_999 + DAY_SECONDS, true), Err(GateError::DayWindowNotElapsed));
+        assert_eq!(check_page(&day, &unaligned, 0, 5_000 + DAY_SECONDS, true), Ok(PageGate::StartDay));
+
+        let aligned = DayConfig { align_to_utc_midnight: true, ..Default::default() };
+        let mut day = DayState::default();
+        start_day(&mut day, &aligned, DAY_SECONDS + 30_000).unwrap();
+        assert_eq!(day.day_start_ts, DAY_SECONDS);
+        day.last_distribution_ts = DAY_SECONDS + 80_000;
+        assert_eq!(check_page(&day, &aligned, 0, 2 * DAY_SECONDS, true), Ok(PageGate::StartDay));
+    }
+
+    /// Page 0 at `now_ts` would open a new day
+    fn starts_day(day: &DayState, config: &DayConfig, now_ts: i64) -> bool {
+        check_page(day, config, 0, now_ts, true) == Ok(PageGate::StartDay) && start_day(&mut day.clone(), config, now_ts).is_ok()
+    }
+
+    #[test]
+    fn next_day_start_is_the_first_second_page0_opens_a_day() {
+        let config = DayConfig { max_days_per_period: 2, first_day_not_before: 500, ..Default::default() };
+        let mut day = DayState::default();
+        let mut states = vec![day];
+        start_day(&mut day, &config, 1_000).unwrap();
+        // Open day: its window, not the previous close, decides
+        states.push(day);
+        day.last_distribution_ts = 1_200;
+        states.push(day);
+        start_day(&mut day, &config, 1_200 + DAY_SECONDS).unwrap();
+        day.last_distribution_ts = 1_300 + DAY_SECONDS;
+        // Both day starts of the period are used up
+        states.push(day);
+        for state in states {
+            let ts = state.next_day_start_ts(&config);
+            assert!(starts_day(&state, &config, ts), "{state:?} at {ts}");
+            assert!(!starts_day(&state, &config, ts - 1), "{state:?} at {}", ts - 1);
+        }
+        assert_eq!(day.next_day_start_ts(&config), day.period_start_ts + DAY_LIMIT_PERIOD_SECONDS);
+    }
+
+    #[test]
+    fn completion_of_committed_pages() {
+        assert_eq!(completion_bps(0, 0), None);
+        assert_eq!(completion_bps(3, 0), None);
+        assert_eq!(completion_bps(0, 4), Some(0));
+        assert_eq!(completion_bps(1, 3), Some(3_333));
+        assert_eq!(completion_bps(4, 4), Some(10_000));
+        // Extra pages beyond the commitment can't exceed 100%
+        assert_eq!(completion_bps(5, 4), Some(10_000));
+    }
+
+    #[test]
+    fn day_limit_resets_with_period() {
+        let config = DayConfig { max_days_per_period: 2, ..Default::default() };
+        let mut day = DayState::default();
+        start_day(&mut day, &config, 1_000).unwrap();
+        start_day(&mut day, &config, 1_000 + DAY_SECONDS).unwrap();
+        let before = day;
+        assert_eq!(start_day(&mut day, &config, 1_000 + 2 * DAY_SECONDS), Err(GateError::DayLimitReached));
+        assert_eq!(day, before);
+        start_day(&mut day, &config, 1_000 + DAY_LIMIT_PERIOD_SECONDS).unwrap();
+        assert_eq!(day.days_in_period, 1);
+    }
+
+    proptest! {
+        #[test]
+        fn random_crank_sequences_keep_invariants(
+            align in any::<bool>(),
+            max_days in prop_oneof![Just(0u16), 1u16..4],
+            start_ts in 1_700_000_000i64..1_700_000_000 + DAY_SECONDS,
+            calls in proptest::collection::vec(call(), 1..60),
+        ) {
+            let config = DayConfig { align_to_utc_midnight: align, max_days_per_period: max_days, ..Default::default() };
+            run(config, start_ts, &calls)?;
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Append-only Merkle tree over a day's `(investor, amount)` payouts.
+//!
+//! `Progress` keeps only the leaf count and one frontier node per level, so pages can add
+//! their payouts as they run and the day's close can emit the root without holding every
+//! leaf. Leaves and nodes are domain-separated sha256 hashes; empty slots hash as zero
+//! subtrees, so the root matches a full binary tree of depth [`DEPTH`] padded with
+//! `[0; 32]` leaves.
+
+use anchor_lang::solana_program::hash::hashv;
+
+/// Up to 65,536 payouts per day
+pub const DEPTH: usize = 16;
+pub const MAX_LEAVES: u32 = 1 << DEPTH;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeFull;
+
+/// Payout tree fields of `Progress`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutTree {
+    pub count: u32,
+    /// Left sibling waiting at each level for the next subtree to complete it
+    pub frontier: [[u8; 32]; DEPTH],
+}
+
+/// `sha256(0x00 || investor || amount_le)`
+pub fn leaf(investor: &[u8; 32], amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, investor, &amount.to_le_bytes()]).to_bytes()
+}
+
+/// `sha256(0x01 || left || right)`
+pub fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Root of an all-empty subtree at each level (`zero_hashes()[0]` is the empty leaf)
+pub fn zero_hashes() -> [[u8; 32]; DEPTH] {
+    let mut zeros = [[0u8; 32]; DEPTH];
+    for level in 1..DEPTH {
+        zeros[level] = node(&zeros[level - 1], &zeros[level - 1]);
+    }
+    zeros
+}
+
+impl PayoutTree {
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<(), TreeFull> {
+        if self.count >= MAX_LEAVES {
+            return Err(TreeFull);
+        }
+        let mut index = self.count;
+        let mut current = leaf;
+        for level in 0..DEPTH {
+            if index & 1 == 0 {
+                self.frontier[level] = current;
+                break;
+            }
+            current = node(&self.frontier[level], &current);
+            index >>= 1;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let zeros = zero_hashes();
+        let mut size = self.count;
+        let mut current = [0u8; 32];
+        for (level, zero) in zeros.iter().enumerate() {
+            current = if size & 1 == 1 {
+                node(&self.frontier[level], &current)
+            } else {
+                node(&current, zero)
+            };
+            size >>= 1;
+        }
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u8) -> [u8; 32] {
+        [i; 32]
+    }
+
+    /// Root of the full, zero-padded tree, level by level
+    fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = leaves.to_vec();
+        level.resize(MAX_LEAVES as usize, [0u8; 32]);
+        while level.len() > 1 {
+            level = level.chunks(2).map(|pair| node(&pair[0], &pair[1])).collect();
+        }
+        level[0]
+    }
+
+    /// Sibling path of `index`, the way an off-chain prover would build it
+    fn proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+        let mut level: Vec<[u8; 32]> = leaves.to_vec();
+        level.resize(MAX_LEAVES as usize, [0u8; 32]);
+        let mut index = index;
+        let mut path = Vec::new();
+        while level.len() > 1 {
+            path.push(level[index ^ 1]);
+            level = level.chunks(2).map(|pair| node(&pair[0], &pair[1])).collect();
+            index >>= 1;
+        }
+        path
+    }
+
+    #[test]
+    fn root_matches_the_padded_tree() {
+        let leaves: Vec<[u8; 32]> = (0..7u8).map(|i| leaf(&key(i), 1_000 + i as u64)).collect();
+        let mut tree = PayoutTree::default();
+        assert_eq!(tree.root(), naive_root(&[]));
+        for (i, l) in leaves.iter().enumerate() {
+            tree.insert(*l).unwrap();
+            assert_eq!(tree.root(), naive_root(&leaves[..=i]));
+        }
+    }
+
+    #[test]
+    fn proof_verifies_against_the_root() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| leaf(&key(i), 10 * i as u64)).collect();
+        let mut tree = PayoutTree::default();
+        for l in &leaves {
+            tree.insert(*l).unwrap();
+        }
+        let index = 3;
+        let mut current = leaves[index];
+        for (level, sibling) in proof(&leaves, index).iter().enumerate() {
+            current = if (index >> level) & 1 == 0 { node(&current, sibling) } else { node(sibling, &current) };
+        }
+        assert_eq!(current, tree.root());
+        // A different amount doesn't prove
+        assert_ne!(leaf(&key(3), 31), leaves[index]);
+    }
+
+    #[test]
+    fn refuses_leaves_past_capacity() {
+        let mut tree = PayoutTree { count: MAX_LEAVES, ..Default::default() };
+        assert_eq!(tree.insert(leaf(&key(0), 1)), Err(TreeFull));
+    }
+}
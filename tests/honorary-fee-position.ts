@@ -12,12 +12,22 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
   createMint,
+  createAccount,
   mintTo,
   getOrCreateAssociatedTokenAccount,
   getAssociatedTokenAddressSync,
+  freezeAccount,
+  thawAccount,
+  getMint,
+  getAccount,
 } from "@solana/spl-token";
+const MEMO_PROGRAM_ID = new PublicKey(
+  "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+);
+
 // import { CpAmmStub } from "../target/types/cp_amm_stub";
 import { assert } from "chai";
+import { createHash } from "crypto";
 
 // Set up wallet if not provided
 if (!process.env.ANCHOR_WALLET) {
@@ -86,6 +96,377 @@ describe("honorary_fee_position (local testing)", () => {
       payer.payer
     );
   }
+
+  // Optional policy settings with every feature switched off
+  function defaultPolicyOptions(overrides: any = {}) {
+    return {
+      transferMemo: false,
+      alignToUtcMidnight: false,
+      baseFeeMode: { strict: {} },
+      reinvestBps: 0,
+      baseTolerance: new anchor.BN(0),
+      maxDaysPerPeriod: 0,
+      claimMaxAgeSecs: 0,
+      maxCreatorPayoutPerCall: new anchor.BN(0),
+      shareTiers: [],
+      settlementDelaySecs: 0,
+      minCreatorPayout: new anchor.BN(0),
+      verbosity: { standard: {} },
+      explicitClaimAccounting: false,
+      creatorVestingSecs: 0,
+      streamLedger: false,
+      creatorPerPage: false,
+      skipTrailingAccounts: false,
+      bulkLockIndex: false,
+      carryDestination: { creator: {} },
+      dustRecipient: PublicKey.default,
+      shareOracle: PublicKey.default,
+      shareOracleProgram: PublicKey.default,
+      oracleMinBps: 0,
+      oracleMaxBps: 0,
+      minInvestorsForDay: 0,
+      dayEscrow: false,
+      divergenceMode: { off: {} },
+      divergenceTolerance: new anchor.BN(0),
+      weightBasis: { locked: {} },
+      feeClaimStrategy: { positionFee: {} },
+      lifetimeInvestorCap: null,
+      pausedStreams: { include: {} },
+      investorIdentity: { stream: {} },
+      roundingBeneficiary: { creator: {} },
+      initialDelaySecs: 0,
+      roundedToZero: { dust: {} },
+      claimAccountsFirst: false,
+      snapshotMaxAgeSecs: 0,
+      payoutMint: PublicKey.default,
+      conversionRate: new anchor.BN(0),
+      conversionQuoteRecipient: PublicKey.default,
+      retentionBps: 0,
+      minClaimToDistribute: new anchor.BN(0),
+      claimToleranceBps: 0,
+      minLockedToParticipate: new anchor.BN(0),
+      dailyCapTokens: null,
+      mostlyDustBps: 0,
+      ...overrides,
+    };
+  }
+
+  // Creates and initializes a fresh vault (own policy, progress and treasury)
+  async function setupVault(
+    params: {
+      y0?: number;
+      investorFeeShareBps?: number;
+      dailyCap?: number | null;
+      minPayout?: number;
+      dustThreshold?: number;
+      options?: any;
+      fund?: number;
+      feeVault?: PublicKey;
+      vaultKp?: Keypair;
+      pool?: PublicKey;
+    } = {}
+  ) {
+    const vaultKp = params.vaultKp ?? Keypair.generate();
+    const [policy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("policy"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const [progress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("progress"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const [ownerPda, bump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vault"),
+        vaultKp.publicKey.toBuffer(),
+        Buffer.from("investor_fee_pos_owner"),
+      ],
+      program.programId
+    );
+    const treasury = getAssociatedTokenAddressSync(quoteMint, ownerPda, true);
+    const dailyCap =
+      params.dailyCap === undefined ? 1000000 : params.dailyCap;
+
+    const initSig = await program.methods
+      .initializeHonoraryPosition(
+        bump,
+        new anchor.BN(params.y0 ?? 1000000),
+        params.investorFeeShareBps ?? 5000,
+        dailyCap === null ? null : new anchor.BN(dailyCap),
+        new anchor.BN(params.minPayout ?? 1000),
+        new anchor.BN(params.dustThreshold ?? 100),
+        defaultPolicyOptions(params.options)
+      )
+      .accounts({
+        initializer: payer.publicKey,
+        vault: vaultKp.publicKey,
+        policy,
+        progress,
+        investorFeePosOwnerPda: ownerPda,
+        honoraryPosition: Keypair.generate().publicKey,
+        programQuoteTreasury: treasury,
+        pool: params.pool ?? Keypair.generate().publicKey,
+        poolQuoteMint: quoteMint,
+        poolBaseMint: baseMint,
+        poolFeeVault: params.feeVault ?? null,
+        cpAmmProgram: Keypair.generate().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([payer.payer])
+      .rpc({ commitment: "confirmed" });
+
+    if (params.fund) {
+      await mintTo(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        treasury,
+        payer.publicKey,
+        params.fund
+      );
+    }
+
+    return { vault: vaultKp.publicKey, policy, progress, ownerPda, bump, treasury, initSig };
+  }
+
+  // Creates `count` mock stream accounts, each paired with a distinct investor ATA
+  async function createInvestorPairs(count: number) {
+    const remainingAccounts = [];
+    const streams: PublicKey[] = [];
+    const atas: PublicKey[] = [];
+    const accountSize = 32;
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(accountSize);
+
+    for (let i = 0; i < count; i++) {
+      const streamKeypair = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: payer.publicKey,
+            newAccountPubkey: streamKeypair.publicKey,
+            lamports,
+            space: accountSize,
+            programId: SystemProgram.programId,
+          })
+        ),
+        [payer.payer, streamKeypair]
+      );
+
+      const investorAta = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        Keypair.generate().publicKey
+      );
+
+      streams.push(streamKeypair.publicKey);
+      atas.push(investorAta.address);
+      remainingAccounts.push(
+        { pubkey: streamKeypair.publicKey, isSigner: false, isWritable: false },
+        { pubkey: investorAta.address, isSigner: false, isWritable: true }
+      );
+    }
+
+    return { remainingAccounts, streams, atas };
+  }
+
+  // Creates a program-owned mock stream with `total` locked, optionally vesting
+  // linearly between vestStartTs and vestEndTs (local-testing only)
+  async function createMockStream(
+    total: number | anchor.BN,
+    vestStartTs = 0,
+    vestEndTs = 0,
+    recipientTokens?: PublicKey
+  ): Promise<PublicKey> {
+    const streamKeypair = Keypair.generate();
+    // Room for the recipient token account after created_at
+    const accountSize = 72;
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(accountSize);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: streamKeypair.publicKey,
+          lamports,
+          space: accountSize,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, streamKeypair]
+    );
+    await program.methods
+      .writeMockStream(
+        new anchor.BN(total.toString()),
+        new anchor.BN(vestStartTs),
+        new anchor.BN(vestEndTs)
+      )
+      .accounts({ stream: streamKeypair.publicKey })
+      .rpc();
+    if (recipientTokens) {
+      await program.methods
+        .writeMockStreamRecipient(recipientTokens)
+        .accounts({ stream: streamKeypair.publicKey })
+        .rpc();
+    }
+    return streamKeypair.publicKey;
+  }
+
+  // Creates a program-owned mock honorary position whose claims report the
+  // given quote/base fees (local-testing only)
+  async function createMockPosition(
+    quoteFees: number,
+    baseFees: number,
+    identity?: { pool: PublicKey; owner: PublicKey }
+  ): Promise<PublicKey> {
+    const positionKeypair = Keypair.generate();
+    // With an identity, its pool and owner follow the fees
+    const accountSize = identity ? 16 + 32 + 32 : 16;
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(accountSize);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: positionKeypair.publicKey,
+          lamports,
+          space: accountSize,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, positionKeypair]
+    );
+    await program.methods
+      .writeMockPosition(new anchor.BN(quoteFees), new anchor.BN(baseFees))
+      .accounts({ position: positionKeypair.publicKey })
+      .rpc();
+    if (identity) {
+      await program.methods
+        .writeMockPositionIdentity(identity.pool, identity.owner)
+        .accounts({ position: positionKeypair.publicKey })
+        .rpc();
+    }
+    return positionKeypair.publicKey;
+  }
+
+  // Decodes the program events emitted by a confirmed transaction
+  async function eventsOf(sig: string) {
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(
+      program.programId,
+      new anchor.BorshCoder(program.idl)
+    );
+    return Array.from(parser.parseLogs(tx.meta.logMessages));
+  }
+
+  // Pairs existing stream accounts with fresh investor ATAs
+  async function pairStreams(streams: PublicKey[]) {
+    const remainingAccounts = [];
+    const atas: PublicKey[] = [];
+    for (const stream of streams) {
+      const investorAta = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        Keypair.generate().publicKey
+      );
+      atas.push(investorAta.address);
+      remainingAccounts.push(
+        { pubkey: stream, isSigner: false, isWritable: false },
+        { pubkey: investorAta.address, isSigner: false, isWritable: true }
+      );
+    }
+    return { remainingAccounts, streams, atas };
+  }
+
+  async function tokenBalance(ata: PublicKey): Promise<number> {
+    const balance = await provider.connection.getTokenAccountBalance(ata);
+    return parseInt(balance.value.amount);
+  }
+
+  // Fails unless `promise` rejects with an error naming `name`, which it returns (for its logs);
+  // a call that succeeds fails too
+  async function expectError(promise: Promise<any>, name: string): Promise<any> {
+    let error;
+    try {
+      await promise;
+    } catch (err) {
+      error = err;
+    }
+    assert.ok(error, `Expected ${name}`);
+    assert.include(error.toString(), name);
+    return error;
+  }
+
+  // Simulates preview_distribution and returns its DistributionPreview event
+  async function previewOf(
+    v: Awaited<ReturnType<typeof setupVault>>,
+    remainingAccounts: any[] = []
+  ) {
+    const sim = await program.methods
+      .previewDistribution()
+      .accounts({
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        poolQuoteMint: quoteMint,
+      })
+      .remainingAccounts(remainingAccounts)
+      .simulate();
+    return sim.events.find((e) => e.name === "distributionPreview").data;
+  }
+
+  // Builds a crank_distribute call for a vault created by setupVault
+  function crank(
+    v: Awaited<ReturnType<typeof setupVault>>,
+    pageIndex: number,
+    isLastPage: boolean,
+    remainingAccounts: any[],
+    extraAccounts: any = {},
+    claimNonce: number | null = null,
+    observedDayTs: anchor.BN | null = null
+  ) {
+    return program.methods
+      .crankDistribute(
+        new anchor.BN(pageIndex),
+        isLastPage,
+        claimNonce === null ? null : new anchor.BN(claimNonce),
+        observedDayTs
+      )
+      .accounts({
+        cranker: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        honoraryPosition: Keypair.generate().publicKey,
+        programQuoteTreasury: v.treasury,
+        creatorQuoteAta: creatorQuoteAta,
+        pool: Keypair.generate().publicKey,
+        poolQuoteMint: quoteMint,
+        poolBaseMint: baseMint,
+        cpAmmProgram: Keypair.generate().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        ...extraAccounts,
+      })
+      .remainingAccounts(remainingAccounts)
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+          units: 400000,
+        }),
+      ])
+      .signers([payer.payer]);
+  }
+
   before(async () => {
     console.log("Setting up test environment...");
 
@@ -95,7 +476,7 @@ describe("honorary_fee_position (local testing)", () => {
         provider.connection,
         payer.payer,
         payer.publicKey,
-        null,
+        payer.publicKey,
         6
       );
 
@@ -167,7 +548,8 @@ describe("honorary_fee_position (local testing)", () => {
           5000, // 50% investor fee share
           new anchor.BN(1000000), // daily cap
           new anchor.BN(1000), // min payout
-          new anchor.BN(100) // dust threshold
+          new anchor.BN(100), // dust threshold
+          defaultPolicyOptions()
         )
         .accounts({
           initializer: payer.publicKey,
@@ -410,9 +792,10 @@ describe("honorary_fee_position (local testing)", () => {
       // Run the crank (page_index = 0 will use normal fees)
       const tx = await program.methods
         .crankDistribute(
-          bumps.investorFeeOwner,
           new anchor.BN(0), // page_index = 0 (normal fees)
-          true
+          true,
+          null,
+          null
         )
         .accounts({
           cranker: payer.publicKey,
@@ -507,7 +890,8 @@ describe("honorary_fee_position (local testing)", () => {
           5000,
           new anchor.BN(1000000),
           new anchor.BN(1000),
-          new anchor.BN(100)
+          new anchor.BN(100),
+          defaultPolicyOptions()
         )
         .accounts({
           initializer: payer.publicKey,
@@ -577,9 +961,10 @@ describe("honorary_fee_position (local testing)", () => {
       try {
         await program.methods
           .crankDistribute(
-            baseTestBump,
             new anchor.BN(999), // This should trigger base fee detection
-            true
+            true,
+            null,
+            null
           )
           .accounts({
             cranker: payer.publicKey,
@@ -730,9 +1115,10 @@ describe("honorary_fee_position (local testing)", () => {
       // Run crank - should route 100% to creator since no locked amounts
       const tx = await program.methods
         .crankDistribute(
-          bumps.investorFeeOwner,
           new anchor.BN(1), // Different page index
-          true
+          true,
+          null,
+          null
         )
         .accounts({
           cranker: payer.publicKey,
@@ -815,7 +1201,8 @@ describe("honorary_fee_position (local testing)", () => {
           5000, // 50% investor fee share
           new anchor.BN(100), // Very low daily cap: 100 tokens
           new anchor.BN(50), // High dust threshold: 50 tokens
-          new anchor.BN(25) // Dust threshold: 25 tokens
+          new anchor.BN(25), // Dust threshold: 25 tokens
+          defaultPolicyOptions()
         )
         .accounts({
           initializer: payer.publicKey,
@@ -898,7 +1285,7 @@ describe("honorary_fee_position (local testing)", () => {
 
       // Run crank with daily cap constraints
       const tx = await program.methods
-        .crankDistribute(lowCapBump, new anchor.BN(0), true)
+        .crankDistribute(new anchor.BN(0), true, null, null)
         .accounts({
           cranker: payer.publicKey,
           vault: lowCapVault.publicKey,
@@ -997,7 +1384,7 @@ describe("honorary_fee_position (local testing)", () => {
       // Try to trigger base fee error by using wrong mint order or configuration
       try {
         await program.methods
-          .crankDistribute(bumps.investorFeeOwner, new anchor.BN(2), true)
+          .crankDistribute(new anchor.BN(2), true, null, null)
           .accounts({
             cranker: payer.publicKey,
             vault: vault.publicKey,
@@ -1135,9 +1522,10 @@ describe("honorary_fee_position (local testing)", () => {
       // Run crank
       const tx = await program.methods
         .crankDistribute(
-          bumps.investorFeeOwner,
           new anchor.BN(3), // Different page
-          true
+          true,
+          null,
+          null
         )
         .accounts({
           cranker: payer.publicKey,
@@ -1205,4 +1593,4768 @@ describe("honorary_fee_position (local testing)", () => {
       throw error;
     }
   });
+
+  it("attaches an SPL memo to each investor transfer when enabled", async () => {
+    const v = await setupVault({
+      options: { transferMemo: true },
+      fund: 1000000,
+    });
+    const { remainingAccounts } = await createInvestorPairs(2);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      memoProgram: MEMO_PROGRAM_ID,
+    }).rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const keys = tx.transaction.message.getAccountKeys({
+      accountKeysFromLookups: tx.meta.loadedAddresses,
+    });
+    const memoIxs = tx.meta.innerInstructions
+      .flatMap((inner) => inner.instructions)
+      .filter((ix) => keys.get(ix.programIdIndex).equals(MEMO_PROGRAM_ID));
+
+    assert.ok(memoIxs.length > 0, "Memo CPI should be present");
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    const expectedMemo = `day_ts:${progressAccount.dayStartTs.toString()} page_index:0`;
+    for (const ix of memoIxs) {
+      assert.equal(
+        Buffer.from(anchor.utils.bytes.bs58.decode(ix.data)).toString(),
+        expectedMemo
+      );
+    }
+  });
+
+  it("rejects memo-enabled cranks without the memo program", async () => {
+    const v = await setupVault({
+      options: { transferMemo: true },
+      fund: 1000000,
+    });
+    const { remainingAccounts } = await createInvestorPairs(1);
+
+    await expectError(crank(v, 0, true, remainingAccounts).rpc(), "MemoProgramMissing");
+  });
+
+  it("rejects carry that would exceed the treasury balance", async () => {
+    // Every payout is below min_payout, so the whole investor share becomes
+    // carry while the (unfunded) treasury holds nothing to back it
+    const v = await setupVault({ minPayout: 1_000_000_000 });
+    const { remainingAccounts } = await createInvestorPairs(3);
+
+    await expectError(crank(v, 0, false, remainingAccounts).rpc(), "CarryExceedsTreasury");
+
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.carryLamports.toString(), "0");
+  });
+
+  it("accepts carry backed by the treasury balance", async () => {
+    const v = await setupVault({ minPayout: 1_000_000_000, fund: 1000000 });
+    const { remainingAccounts } = await createInvestorPairs(3);
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.ok(progressAccount.carryLamports.toNumber() > 0);
+    assert.ok(progressAccount.carryLamports.toNumber() <= 1000000);
+  });
+
+  it("aligns day_start_ts to UTC midnight when configured", async () => {
+    const DAY = 86400;
+    const v = await setupVault({
+      options: { alignToUtcMidnight: true },
+      fund: 1000000,
+    });
+    const { remainingAccounts } = await createInvestorPairs(2);
+
+    await crank(v, 0, true, remainingAccounts).rpc();
+
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    const dayStart = progressAccount.dayStartTs.toNumber();
+    const closedAt = progressAccount.lastDistributionTs.toNumber();
+    assert.equal(dayStart % DAY, 0, "day start should sit on a boundary");
+    assert.ok(dayStart <= closedAt && closedAt < dayStart + DAY);
+
+    // The next day opens at dayStart + DAY, independent of when this day was
+    // cranked; until then page 0 is gated
+    await expectError(crank(v, 0, true, remainingAccounts).rpc(), "DayWindowNotElapsed");
+  });
+
+  it("evaluates locked amounts at the page-0 reference time on every page", async () => {
+    const now = (await provider.connection.getBlockTime(
+      await provider.connection.getSlot()
+    ))!;
+    const v = await setupVault({ y0: 10_000_000, fund: 2_000_000 });
+
+    // One stream vests quickly across the day's pages, the other stays locked
+    const vesting = await createMockStream(1_000_000, now - 30, now + 30);
+    const fixed = await createMockStream(500_000);
+    const { remainingAccounts, atas } = await pairStreams([vesting, fixed]);
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+    const page0 = [await tokenBalance(atas[0]), await tokenBalance(atas[1])];
+
+    // Let the vesting stream unlock further before the next page
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+
+    await crank(v, 1, true, remainingAccounts).rpc();
+    const page1 = [
+      (await tokenBalance(atas[0])) - page0[0],
+      (await tokenBalance(atas[1])) - page0[1],
+    ];
+
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(
+      progressAccount.lockedReferenceTs.toString(),
+      progressAccount.dayStartTs.toString()
+    );
+
+    // Same locked snapshot on both pages => same vesting:fixed payout ratio
+    const ratio0 = page0[0] / page0[1];
+    const ratio1 = page1[0] / page1[1];
+    assert.ok(
+      Math.abs(ratio0 - ratio1) < 0.01,
+      `ratios diverged: ${ratio0} vs ${ratio1}`
+    );
+  });
+
+  it("fails on base fees in strict mode", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const position = await createMockPosition(100000, 5000);
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(300000),
+    ]);
+
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: position,
+      }).rpc(),
+      "BaseFeesObserved"
+    );
+  });
+
+  it("quarantines base fees and still distributes quote fees", async () => {
+    const v = await setupVault({
+      options: { baseFeeMode: { quarantine: {} } },
+      fund: 1000000,
+    });
+    const position = await createMockPosition(100000, 5000);
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(300000),
+    ]);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: position,
+    }).rpc({ commitment: "confirmed" });
+
+    const events = await eventsOf(sig);
+    const quarantined = events.find((e) => e.name === "baseFeesQuarantined");
+    assert.ok(quarantined, "BaseFeesQuarantined should be emitted");
+    assert.equal(quarantined.data.baseAmount.toString(), "5000");
+
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.quarantinedBaseFees.toString(), "5000");
+    assert.ok((await tokenBalance(atas[0])) > 0, "quote fees still paid out");
+  });
+
+  it("previews remaining daily cap as pages distribute", async () => {
+    const v = await setupVault({ dailyCap: 60000, fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(500000),
+      await createMockStream(500000),
+    ]);
+
+    const before = await previewOf(v, remainingAccounts);
+    assert.equal(before.remainingCap.toString(), "60000");
+    assert.equal(before.treasuryBalance.toString(), "1000000");
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+    let progressAccount = await program.account.progress.fetch(v.progress);
+    const afterPage0 = await previewOf(v);
+    const distributed0 = progressAccount.cumulativeDistributedToday.toNumber();
+    assert.ok(distributed0 > 0);
+    assert.equal(afterPage0.remainingCap.toNumber(), 60000 - distributed0);
+
+    await crank(v, 1, false, remainingAccounts).rpc();
+    progressAccount = await program.account.progress.fetch(v.progress);
+    const afterPage1 = await previewOf(v);
+    const distributed1 = progressAccount.cumulativeDistributedToday.toNumber();
+    assert.ok(distributed1 > distributed0);
+    assert.equal(afterPage1.remainingCap.toNumber(), 60000 - distributed1);
+    assert.ok(afterPage1.remainingCap.toNumber() < afterPage0.remainingCap.toNumber());
+  });
+
+  it("creates the treasury independently of init and keeps it idempotent", async () => {
+    const vaultKp = Keypair.generate();
+    const [ownerPda, bump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vault"),
+        vaultKp.publicKey.toBuffer(),
+        Buffer.from("investor_fee_pos_owner"),
+      ],
+      program.programId
+    );
+    const treasury = getAssociatedTokenAddressSync(quoteMint, ownerPda, true);
+    const [policy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("policy"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const createTreasury = () =>
+      program.methods
+        .createTreasury()
+        .accounts({
+          payer: payer.publicKey,
+          vault: vaultKp.publicKey,
+          policy,
+          investorFeePosOwnerPda: ownerPda,
+          programQuoteTreasury: treasury,
+          poolQuoteMint: quoteMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    // Create and pre-fund before the vault is initialized
+    await createTreasury();
+    await mintTo(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      treasury,
+      payer.publicKey,
+      250000
+    );
+    // Repeating is a no-op
+    await createTreasury();
+    assert.equal(await tokenBalance(treasury), 250000);
+
+    const [progress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("progress"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeHonoraryPosition(
+        bump,
+        new anchor.BN(1000000),
+        5000,
+        new anchor.BN(1000000),
+        new anchor.BN(1000),
+        new anchor.BN(100),
+        defaultPolicyOptions()
+      )
+      .accounts({
+        initializer: payer.publicKey,
+        vault: vaultKp.publicKey,
+        policy,
+        progress,
+        investorFeePosOwnerPda: ownerPda,
+        honoraryPosition: Keypair.generate().publicKey,
+        programQuoteTreasury: treasury,
+        pool: Keypair.generate().publicKey,
+        poolQuoteMint: quoteMint,
+        poolBaseMint: baseMint,
+        cpAmmProgram: Keypair.generate().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    // Init reuses the pre-created treasury and snapshots its balance
+    const progressAccount = await program.account.progress.fetch(progress);
+    assert.equal(progressAccount.treasurySnapshot.toString(), "250000");
+    assert.equal((await program.account.policy.fetch(policy)).treasury.toBase58(), treasury.toBase58());
+
+    // Repair path after init is still a no-op
+    await createTreasury();
+    assert.equal(await tokenBalance(treasury), 250000);
+  });
+
+  it("handles locked totals beyond u64 without truncating the share", async () => {
+    // Two streams near u64::MAX: locked_total only fits in u128 and the raw
+    // f_locked value overflows u64 before it is clamped to 10_000 bps
+    const nearMax = new anchor.BN("18446744073709551000");
+    const v = await setupVault({ y0: 1000000, fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(nearMax),
+      await createMockStream(nearMax),
+    ]);
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+
+    // Full 50% share of the 100_000 stub claim, split evenly
+    assert.equal(await tokenBalance(atas[0]), 25000);
+    assert.equal(await tokenBalance(atas[1]), 25000);
+  });
+
+  it("clamps the creator remainder to the available treasury balance", async () => {
+    const v = await setupVault({ fund: 1000, minPayout: 1000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(1000000),
+    ]);
+
+    // Page 0: a 2-unit claim yields a 1-unit investor share that is dusted
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(2, 0),
+    }).rpc();
+    let progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.carryLamports.toString(), "1");
+
+    // Zeroing the snapshot makes remainder = balance + carry, one lamport more
+    // than the treasury actually holds
+    await program.methods
+      .setMockTreasurySnapshot(new anchor.BN(0))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const clamped = (await eventsOf(sig)).find(
+      (e) => e.name === "remainderClamped"
+    );
+    assert.ok(clamped, "RemainderClamped should be emitted");
+    assert.equal(clamped.data.computedRemainder.toString(), "1001");
+    assert.equal(clamped.data.clampedTo.toString(), "1000");
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 1000);
+    assert.equal(await tokenBalance(v.treasury), 0);
+  });
+
+  it("reports which pages have been processed", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const status = async (pageIndex: number) => {
+      const sim = await program.methods
+        .isPageProcessed(new anchor.BN(pageIndex))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .simulate();
+      return sim.events.find((e) => e.name === "pageProcessedStatus").data;
+    };
+
+    assert.isFalse((await status(0)).processed);
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+
+    const page0 = await status(0);
+    assert.isTrue(page0.processed);
+    assert.equal(page0.cursor.toString(), "0");
+    assert.isFalse((await status(1)).processed);
+    assert.isFalse((await status(2 ** 40)).processed);
+  });
+
+  it("requires the authority threshold for sensitive instructions", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const cosignerA = Keypair.generate();
+    const cosignerB = Keypair.generate();
+    const outsider = Keypair.generate();
+    const adminAccounts = {
+      authority: payer.publicKey,
+      vault: v.vault,
+      policy: v.policy,
+      progress: v.progress,
+    };
+    const coSigner = (kp: Keypair) => [
+      { pubkey: kp.publicKey, isSigner: true, isWritable: false },
+    ];
+
+    // The initializer (default 1-of-1 authority) installs a 2-of-3 set
+    await program.methods
+      .setPolicyAuthority(
+        [payer.publicKey, cosignerA.publicKey, cosignerB.publicKey],
+        2
+      )
+      .accounts(adminAccounts)
+      .rpc();
+
+    // Threshold not met: initializer alone, or with a non-member co-signer
+    await expectError(program.methods.setPaused(true).accounts(adminAccounts).rpc(), "Unauthorized");
+    await expectError(
+      program.methods
+        .setPaused(true)
+        .accounts(adminAccounts)
+        .remainingAccounts(coSigner(outsider))
+        .signers([outsider])
+        .rpc(),
+      "Unauthorized"
+    );
+    assert.isFalse((await program.account.policy.fetch(v.policy)).paused);
+
+    // Threshold met with a member co-signer
+    await program.methods
+      .setPaused(true)
+      .accounts(adminAccounts)
+      .remainingAccounts(coSigner(cosignerA))
+      .signers([cosignerA])
+      .rpc();
+    assert.isTrue((await program.account.policy.fetch(v.policy)).paused);
+
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    await expectError(crank(v, 0, true, remainingAccounts).rpc(), "ProgramPaused");
+
+    // update_policy follows the same rule
+    await program.methods
+      .updatePolicy(
+        new anchor.BN(1000000),
+        2500,
+        new anchor.BN(500000),
+        new anchor.BN(1000),
+        new anchor.BN(100),
+        defaultPolicyOptions()
+      )
+      .accounts(adminAccounts)
+      .remainingAccounts(coSigner(cosignerB))
+      .signers([cosignerB])
+      .rpc();
+    const policyAccount = await program.account.policy.fetch(v.policy);
+    assert.equal(policyAccount.investorFeeShareBps, 2500);
+    assert.equal(policyAccount.dailyCap.toString(), "500000");
+  });
+
+  it("lets the authority withdraw treasury funds in an emergency", async () => {
+    const v = await setupVault({ fund: 300000 });
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+
+    await program.methods
+      .emergencyWithdraw(new anchor.BN(120000))
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        destination: creatorQuoteAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    assert.equal(await tokenBalance(v.treasury), 180000);
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 120000);
+
+    const intruder = Keypair.generate();
+    await expectError(
+      program.methods
+        .emergencyWithdraw(new anchor.BN(1))
+        .accounts({
+          authority: intruder.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          investorFeePosOwnerPda: v.ownerPda,
+          programQuoteTreasury: v.treasury,
+          destination: creatorQuoteAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([intruder])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("emits PageReplayed instead of a payout event for a replayed page", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    await crank(v, 0, false, remainingAccounts).rpc();
+    const firstSig = await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(50000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const firstEvents = await eventsOf(firstSig);
+    assert.ok(firstEvents.find((e) => e.name === "investorPayoutPage"));
+    assert.notOk(firstEvents.find((e) => e.name === "pageReplayed"));
+    const balanceAfterFirst = await tokenBalance(atas[0]);
+
+    // A distinct position keeps the retry from being deduplicated as the same transaction
+    const replaySig = await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(50000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const replayEvents = await eventsOf(replaySig);
+    const replayed = replayEvents.find((e) => e.name === "pageReplayed");
+    assert.ok(replayed, "PageReplayed should be emitted");
+    assert.equal(replayed.data.pageIndex.toString(), "1");
+    assert.notOk(replayEvents.find((e) => e.name === "investorPayoutPage"));
+    assert.equal(await tokenBalance(atas[0]), balanceAfterFirst);
+  });
+
+  it("replays a page before reading its locks", async () => {
+    // Locked above y0: a computed page would emit LockedExceedsBaseline
+    const v = await setupVault({ y0: 100000, fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, false, remainingAccounts).rpc();
+    await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(50000, 0),
+    }).rpc();
+
+    const replaySig = await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(50000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const names = (await eventsOf(replaySig)).map((e) => e.name);
+    assert.include(names, "pageReplayed");
+    assert.notInclude(names, "lockedExceedsBaseline");
+  });
+
+  it("applies an updated y0 baseline to subsequent share math", async () => {
+    const v = await setupVault({ y0: 1000000, fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(200000),
+    ]);
+    const adminAccounts = {
+      authority: payer.publicKey,
+      vault: v.vault,
+      policy: v.policy,
+      progress: v.progress,
+    };
+    const updateY0 = (y0: number) =>
+      program.methods
+        .updatePolicy(
+          new anchor.BN(y0),
+          5000,
+          new anchor.BN(1000000),
+          new anchor.BN(1000),
+          new anchor.BN(100),
+          defaultPolicyOptions()
+        )
+        .accounts(adminAccounts);
+
+    // 200k locked of 1M baseline -> f_locked 20%
+    assert.equal((await previewOf(v, remainingAccounts)).projectedInvestor.toString(), "200000");
+
+    const sig = await updateY0(400000).rpc({ commitment: "confirmed" });
+    const updated = (await eventsOf(sig)).find((e) => e.name === "baselineUpdated");
+    assert.ok(updated, "BaselineUpdated should be emitted");
+    assert.equal(updated.data.previousY0.toString(), "1000000");
+    assert.equal(updated.data.y0.toString(), "400000");
+    await expectError(updateY0(0).rpc(), "InvalidBaseline");
+
+    // 200k of 400k -> f_locked 50%, capped by the 50% investor share
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 50000);
+
+    // Accepted mid-day, but the open day keeps the 400k it started with
+    await updateY0(800000).rpc();
+    const next = await pairStreams([await createMockStream(200000)]);
+    await crank(v, 1, false, next.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(next.atas[0]), 50000);
+  });
+
+  it("warns when locked amounts exceed the y0 baseline", async () => {
+    const v = await setupVault({ y0: 500000, fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(400000),
+    ]);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const warning = (await eventsOf(sig)).find(
+      (e) => e.name === "lockedExceedsBaseline"
+    );
+    assert.ok(warning, "LockedExceedsBaseline should be emitted");
+    assert.equal(warning.data.lockedTotal.toString(), "800000");
+    assert.equal(warning.data.y0.toString(), "500000");
+    // f_locked saturates, so investors still get the full 50% share
+    assert.equal(await tokenBalance(atas[0]), 25000);
+    assert.equal(await tokenBalance(atas[1]), 25000);
+  });
+
+  it("queues a frozen investor's payout and lets them claim it later", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const investor = Keypair.generate();
+    const investorAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        investor.publicKey
+      )
+    ).address;
+    await freezeAccount(
+      provider.connection,
+      payer.payer,
+      investorAta,
+      quoteMint,
+      payer.payer
+    );
+
+    const [pending] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_withdrawal"), v.vault.toBuffer(), investorAta.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openPendingWithdrawal()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        investorAta,
+        pendingWithdrawal: pending,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // A pending withdrawal of another investor can't stand in for this stream's ATA
+    const stream = await createMockStream(400000, 0, 0, investorAta);
+    const otherAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer.payer, quoteMint, Keypair.generate().publicKey)
+    ).address;
+    const [otherPending] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_withdrawal"), v.vault.toBuffer(), otherAta.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openPendingWithdrawal()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        investorAta: otherAta,
+        pendingWithdrawal: otherPending,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await expectError(
+      crank(
+        v,
+        0,
+        true,
+        [
+          { pubkey: stream, isSigner: false, isWritable: false },
+          { pubkey: otherPending, isSigner: false, isWritable: true },
+        ],
+        { honoraryPosition: await createMockPosition(100000, 0) }
+      ).rpc(),
+      "InvestorDestinationMismatch"
+    );
+    assert.equal((await program.account.pendingWithdrawal.fetch(otherPending)).amount.toString(), "0");
+
+    // The keeper passes the pending PDA in place of the frozen ATA
+    const sig = await crank(
+      v,
+      0,
+      true,
+      [
+        { pubkey: stream, isSigner: false, isWritable: false },
+        { pubkey: pending, isSigner: false, isWritable: true },
+      ],
+      { honoraryPosition: await createMockPosition(100000, 0) }
+    ).rpc({ commitment: "confirmed" });
+
+    const queued = (await eventsOf(sig)).find((e) => e.name === "payoutQueued");
+    assert.ok(queued, "PayoutQueued should be emitted");
+    assert.equal(queued.data.amount.toString(), "40000");
+    assert.equal(
+      (await program.account.pendingWithdrawal.fetch(pending)).amount.toString(),
+      "40000"
+    );
+    assert.equal(
+      (await program.account.progress.fetch(v.progress)).pendingWithdrawalsTotal.toString(),
+      "40000"
+    );
+
+    await thawAccount(
+      provider.connection,
+      payer.payer,
+      investorAta,
+      quoteMint,
+      payer.payer
+    );
+    const claimAccounts = {
+      investor: investor.publicKey,
+      vault: v.vault,
+      progress: v.progress,
+      pendingWithdrawal: pending,
+      investorAta,
+      investorFeePosOwnerPda: v.ownerPda,
+      programQuoteTreasury: v.treasury,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.methods
+      .claimPending()
+      .accounts(claimAccounts)
+      .signers([investor])
+      .rpc();
+
+    assert.equal(await tokenBalance(investorAta), 40000);
+    assert.equal(
+      (await program.account.pendingWithdrawal.fetch(pending)).amount.toString(),
+      "0"
+    );
+    // The compute budget ix keeps the retry distinct from the first claim
+    await expectError(
+      program.methods
+        .claimPending()
+        .accounts(claimAccounts)
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 200000 }),
+        ])
+        .signers([investor])
+        .rpc(),
+      "NothingPending"
+    );
+  });
+
+  it("reports transfers and compute usage per page", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const reportOf = async (sig: string) =>
+      (await eventsOf(sig)).find((e) => e.name === "pageComputeReport").data;
+
+    // Page 0: one investor, 40% locked -> a single 40k payout
+    const small = await pairStreams([await createMockStream(400000)]);
+    const sig0 = await crank(v, 0, false, small.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const report0 = await reportOf(sig0);
+    assert.equal(report0.investors, 1);
+    assert.equal(report0.transfers, 1);
+    assert.ok(report0.computeUnitsConsumed.toNumber() > 0);
+
+    // Page 1: three investors, 10k each
+    const large = await pairStreams([
+      await createMockStream(100000),
+      await createMockStream(100000),
+      await createMockStream(100000),
+    ]);
+    const sig1 = await crank(v, 1, false, large.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const report1 = await reportOf(sig1);
+    assert.equal(report1.investors, 3);
+    assert.equal(report1.transfers, 3);
+    assert.ok(
+      report1.computeUnitsConsumed.toNumber() >
+        report0.computeUnitsConsumed.toNumber()
+    );
+  });
+
+  it("distributes a staged claim once and rejects a consumed claim nonce", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const claimFees = (position: PublicKey) =>
+      program.methods
+        .claimFees()
+        .accounts({
+          cranker: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          honoraryPosition: position,
+          pool: Keypair.generate().publicKey,
+          cpAmmProgram: Keypair.generate().publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+
+    const stageSig = await claimFees(await createMockPosition(100000, 0));
+    const staged = (await eventsOf(stageSig)).find((e) => e.name === "feesStaged");
+    assert.equal(staged.data.claimNonce.toString(), "1");
+    assert.equal(staged.data.claimedQuote.toString(), "100000");
+
+    // A second claim can't be staged before the first is distributed
+    await expectError(claimFees(await createMockPosition(100000, 0)), "UnconsumedClaim");
+
+    await crank(v, 0, false, remainingAccounts, {}, 1).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+
+    await expectError(crank(v, 1, false, remainingAccounts, {}, 1).rpc(), "StaleClaimNonce");
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("rejects a crank whose owner PDA or treasury authority disagree", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    // Owner PDA account of a different vault
+    const other = await setupVault();
+    try {
+      await crank(v, 0, false, remainingAccounts, {
+        investorFeePosOwnerPda: other.ownerPda,
+      }).rpc();
+      assert.fail("Expected owner PDA rejection");
+    } catch (error) {
+      assert.match(error.toString(), /ConstraintSeeds|OwnerPdaMismatch/);
+    }
+
+    // Treasury not owned by the owner PDA
+    const foreignTreasury = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      Keypair.generate().publicKey
+    );
+    await expectError(
+      crank(v, 0, false, remainingAccounts, {
+        programQuoteTreasury: foreignTreasury.address,
+      }).rpc(),
+      "OwnerPdaMismatch"
+    );
+  });
+
+  it("reinvests reinvest_bps of the claim before the investor split", async () => {
+    await expectError(
+      setupVault({
+        investorFeeShareBps: 9000,
+        options: { reinvestBps: 2000 },
+      }),
+      "InvalidBps"
+    );
+
+    const v = await setupVault({ fund: 1000000, options: { reinvestBps: 2000 } });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const reinvested = (await eventsOf(sig)).find((e) => e.name === "feesReinvested");
+    assert.ok(reinvested, "FeesReinvested should be emitted");
+    assert.equal(reinvested.data.amount.toString(), "20000");
+    // 40% of the remaining 80k
+    assert.equal(await tokenBalance(atas[0]), 32000);
+  });
+
+  it("emits InvestorDusted only for locked investors below min_payout", async () => {
+    const v = await setupVault({ minPayout: 1000, fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(300000),
+      await createMockStream(1000),
+      await createMockStream(0),
+    ]);
+
+    // 301k locked of 1M -> 30.1% of 100k = 30100, split 30000 / 100 / 0
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const dusted = (await eventsOf(sig)).filter((e) => e.name === "investorDusted");
+    assert.equal(dusted.length, 1);
+    assert.equal(dusted[0].data.ata.toBase58(), atas[1].toBase58());
+    assert.equal(dusted[0].data.wouldHaveReceived.toString(), "100");
+    assert.equal(await tokenBalance(atas[0]), 30000);
+    assert.equal(await tokenBalance(atas[1]), 0);
+  });
+
+  it("exposes a checkpoint a restarted keeper can resume from", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const checkpoint = async () => {
+      const sim = await program.methods
+        .getCheckpoint()
+        .accounts({ vault: v.vault, progress: v.progress })
+        .simulate();
+      return sim.events.find((e) => e.name === "resumeCheckpoint").data;
+    };
+    const pageHash = (accounts: { pubkey: PublicKey }[]) =>
+      createHash("sha256")
+        .update(Buffer.concat(accounts.map((a) => a.pubkey.toBuffer())))
+        .digest();
+
+    const fresh = await checkpoint();
+    assert.isFalse(fresh.dayInProgress);
+    assert.equal(fresh.nextPageIndex.toString(), "0");
+
+    const page0 = await pairStreams([await createMockStream(400000)]);
+    const page1 = await pairStreams([await createMockStream(200000)]);
+    await crank(v, 0, false, page0.remainingAccounts).rpc();
+
+    // Keeper "restarts" and rediscovers where it left off
+    const resumed = await checkpoint();
+    assert.isTrue(resumed.dayInProgress);
+    assert.equal(resumed.nextPageIndex.toString(), "1");
+    assert.equal(resumed.pagesProcessed, 1);
+    assert.deepEqual(
+      Buffer.from(resumed.lastPageHash),
+      pageHash(page0.remainingAccounts)
+    );
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(
+      resumed.cumulativeDistributedToday.toString(),
+      progressAccount.cumulativeDistributedToday.toString()
+    );
+
+    await crank(v, resumed.nextPageIndex.toNumber(), true, page1.remainingAccounts).rpc();
+    const closed = await checkpoint();
+    assert.isFalse(closed.dayInProgress);
+    assert.equal(closed.nextPageIndex.toString(), "0");
+    assert.deepEqual(
+      Buffer.from(closed.lastPageHash),
+      pageHash(page1.remainingAccounts)
+    );
+  });
+
+  it("ignores base fees up to base_tolerance and enforces the mode above it", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      options: { baseTolerance: new anchor.BN(100) },
+    });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    // Below and at the tolerance: the page distributes normally
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 50),
+    }).rpc();
+    await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 100),
+    }).rpc();
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.quarantinedBaseFees.toString(), "0");
+    assert.equal(progressAccount.cursor.toString(), "1");
+
+    // Above the tolerance: strict mode still fails
+    await expectError(
+      crank(v, 2, false, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 101),
+      }).rpc(),
+      "BaseFeesObserved"
+    );
+  });
+
+  it("grows page record capacity and cranks beyond the original cap", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const resize = (newCap: number) =>
+      program.methods
+        .resizePageRecords(newCap)
+        .accounts({
+          authority: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    const sizeBefore = (await provider.connection.getAccountInfo(v.progress)).data.length;
+    await resize(12);
+    const sizeAfter = (await provider.connection.getAccountInfo(v.progress)).data.length;
+    assert.equal(sizeAfter - sizeBefore, 2 * 28);
+    assert.equal((await program.account.progress.fetch(v.progress)).pageRecordCap, 12);
+
+    await expectError(resize(12), "InvalidPageRecordCap");
+
+    // 11 pages: one more than the original 10-record capacity
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(100000),
+    ]);
+    for (let page = 0; page <= 10; page++) {
+      await crank(v, page, false, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+    }
+    const progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.pageRecords.length, 11);
+
+    // Resizing is only allowed between days
+    await expectError(resize(20), "DayInProgress");
+  });
+
+  it("resizes page records straight from the default to the ceiling", async () => {
+    const v = await setupVault();
+    const resize = (newCap: number) =>
+      program.methods
+        .resizePageRecords(newCap)
+        .accounts({
+          authority: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    // 10 + 10_240 / 28 = 375: one realloc growing by 10_220 bytes
+    const sizeBefore = (await provider.connection.getAccountInfo(v.progress)).data.length;
+    await resize(375);
+    const sizeAfter = (await provider.connection.getAccountInfo(v.progress)).data.length;
+    assert.equal(sizeAfter - sizeBefore, 365 * 28);
+    assert.equal((await program.account.progress.fetch(v.progress)).pageRecordCap, 375);
+
+    await expectError(resize(376), "InvalidPageRecordCap");
+  });
+
+  it("reconciles the whole day in CreatorPayoutDayClosed", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const page0 = await pairStreams([
+      await createMockStream(200000),
+      await createMockStream(200000),
+    ]);
+    const page1 = await pairStreams([
+      await createMockStream(100000),
+      await createMockStream(0),
+    ]);
+
+    const events = [];
+    const sig0 = await crank(v, 0, false, page0.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    events.push(...(await eventsOf(sig0)));
+    const sig1 = await crank(v, 1, true, page1.remainingAccounts, {
+      honoraryPosition: await createMockPosition(50000, 0),
+    }).rpc({ commitment: "confirmed" });
+    events.push(...(await eventsOf(sig1)));
+
+    const sum = (name: string, field: string) =>
+      events
+        .filter((e) => e.name === name)
+        .reduce((acc, e) => acc + e.data[field].toNumber(), 0);
+    const closed = events.find((e) => e.name === "creatorPayoutDayClosed").data;
+
+    assert.equal(closed.totalClaimed.toNumber(), sum("quoteFeesClaimed", "claimedQuote"));
+    assert.equal(closed.totalClaimed.toNumber(), 150000);
+    assert.equal(
+      closed.totalToInvestors.toNumber(),
+      sum("investorPayoutPage", "pageTotalPayout")
+    );
+    assert.equal(closed.totalToInvestors.toNumber(), 45000);
+    assert.equal(closed.numInvestorsPaid, 3);
+    assert.equal(closed.carryForward.toString(), "0");
+  });
+
+  it("distributes externally recorded claims", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const depositor = Keypair.generate();
+    const depositorAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        depositor.publicKey
+      )
+    ).address;
+    await mintTo(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      depositorAta,
+      payer.publicKey,
+      100000
+    );
+
+    const sig = await program.methods
+      .recordExternalClaim(new anchor.BN(100000))
+      .accounts({
+        depositor: depositor.publicKey,
+        vault: v.vault,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        source: depositorAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([depositor])
+      .rpc({ commitment: "confirmed" });
+    const recorded = (await eventsOf(sig)).find(
+      (e) => e.name === "externalClaimRecorded"
+    );
+    assert.equal(recorded.data.amount.toString(), "100000");
+    assert.equal(await tokenBalance(v.treasury), 1100000);
+
+    // The position itself claims nothing; the external claim is what gets split
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(
+      (await program.account.progress.fetch(v.progress)).externalClaimedPending.toString(),
+      "0"
+    );
+  });
+
+  it("treats page 0 resubmitted mid-day as a retry without resetting the day", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    const before = await program.account.progress.fetch(v.progress);
+    const balanceBefore = await tokenBalance(atas[0]);
+
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    assert.ok(events.find((e) => e.name === "pageReplayed"));
+    assert.notOk(events.find((e) => e.name === "investorPayoutPage"));
+
+    const after = await program.account.progress.fetch(v.progress);
+    assert.equal(after.dayStartTs.toString(), before.dayStartTs.toString());
+    assert.equal(after.cursor.toString(), "1");
+    assert.equal(
+      after.cumulativeDistributedToday.toString(),
+      before.cumulativeDistributedToday.toString()
+    );
+    assert.equal(after.pageRecords.length, 2);
+    assert.equal(await tokenBalance(atas[0]), balanceBefore);
+
+    // The day carries on from the untouched cursor
+    await crank(v, 2, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+  });
+
+  it("limits day starts per 30-day period", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000, options: { maxDaysPerPeriod: 2 } });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const closeDayAndRewind = async () => {
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(10000, 0),
+      }).rpc();
+      // Pretend a full day passed so only the period limit can stop the next day
+      await program.methods
+        .rewindMockDay(new anchor.BN(DAY))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+    };
+
+    await closeDayAndRewind();
+    await closeDayAndRewind();
+    assert.equal((await program.account.progress.fetch(v.progress)).daysInPeriod, 2);
+
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(10000, 0),
+      }).rpc(),
+      "DayLimitReached"
+    );
+  });
+
+  it("requires a fresh claim when the staged claim is older than claim_max_age_secs", async () => {
+    const v = await setupVault({ fund: 1000000, options: { claimMaxAgeSecs: 3600 } });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const claimFees = async (position: PublicKey) =>
+      eventsOf(
+        await program.methods
+          .claimFees()
+          .accounts({
+            cranker: payer.publicKey,
+            vault: v.vault,
+            policy: v.policy,
+            progress: v.progress,
+            honoraryPosition: position,
+            pool: Keypair.generate().publicKey,
+            cpAmmProgram: Keypair.generate().publicKey,
+          })
+          .rpc({ commitment: "confirmed" })
+      );
+
+    await claimFees(await createMockPosition(100000, 0));
+    // Age the staged claim past the limit
+    await program.methods
+      .rewindMockDay(new anchor.BN(7200))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+
+    await expectError(crank(v, 0, false, remainingAccounts, {}, 1).rpc(), "ClaimExpired");
+    assert.equal(await tokenBalance(atas[0]), 0);
+
+    // Re-claiming stages the fresh fees on top of the expired ones
+    const events = await claimFees(await createMockPosition(100000, 0));
+    const staged = events.find((e) => e.name === "feesStaged");
+    assert.equal(staged.data.claimNonce.toString(), "2");
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.stagedClaimQuote.toString(), "200000");
+
+    await expectError(crank(v, 0, false, remainingAccounts, {}, 1).rpc(), "StaleClaimNonce");
+    await crank(v, 0, false, remainingAccounts, {}, 2).rpc();
+    assert.equal(await tokenBalance(atas[0]), 80000);
+  });
+
+  it("settles the creator remainder over two last-page calls", async () => {
+    // The investor's 40k share is below min_payout, so it is carried to the creator
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 100000,
+      options: { maxCreatorPayoutPerCall: new anchor.BN(25000) },
+    });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+
+    const sig0 = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events0 = await eventsOf(sig0);
+    const partial = events0.find((e) => e.name === "creatorPayoutPartial").data;
+    assert.equal(partial.paid.toString(), "25000");
+    assert.equal(partial.remaining.toString(), "15000");
+    assert.notOk(events0.find((e) => e.name === "creatorPayoutDayClosed"));
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore + 25000);
+
+    let progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.lastDistributionTs.toString(), "0");
+    assert.equal(progress.creatorPending.toString(), "15000");
+
+    // Retrying the last page settles the rest and closes the day
+    const sig1 = await crank(v, 0, true, remainingAccounts).rpc({ commitment: "confirmed" });
+    const closed = (await eventsOf(sig1)).find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.creatorPayout.toString(), "40000");
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore + 40000);
+    assert.equal(await tokenBalance(atas[0]), 0);
+
+    progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.creatorPending.toString(), "0");
+    assert.equal(progress.creatorPaidToday.toString(), "40000");
+    assert.ok(progress.lastDistributionTs.toNumber() > 0);
+  });
+
+  it("records a hash and summary of the locked amounts each page used", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const page0 = await pairStreams([
+      await createMockStream(300000),
+      await createMockStream(100000),
+    ]);
+    const page1 = await pairStreams([await createMockStream(200000)]);
+    const locks = (page: { remainingAccounts: { pubkey: PublicKey }[] }, amounts: number[]) =>
+      amounts.map((amount, i) => ({
+        stream: page.remainingAccounts[2 * i].pubkey,
+        ata: page.remainingAccounts[2 * i + 1].pubkey,
+        amount,
+      }));
+    const chain = (prev: Buffer, entries: { stream: PublicKey; ata: PublicKey; amount: number }[]) =>
+      createHash("sha256")
+        .update(
+          Buffer.concat([
+            prev,
+            ...entries.flatMap((e) => [
+              e.stream.toBuffer(),
+              e.ata.toBuffer(),
+              new anchor.BN(e.amount).toArrayLike(Buffer, "le", 8),
+            ]),
+          ])
+        )
+        .digest();
+
+    const sig0 = await crank(v, 0, false, page0.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const snapshot0 = (await eventsOf(sig0)).find((e) => e.name === "locksSnapshot").data;
+    const hash0 = chain(Buffer.alloc(32), locks(page0, [300000, 100000]));
+    assert.equal(snapshot0.investors, 2);
+    assert.equal(snapshot0.pageLockedTotal.toString(), "400000");
+    assert.deepEqual(Buffer.from(snapshot0.dayLocksHash), hash0);
+    // Payouts follow the snapshotted amounts: 40k split 3:1
+    assert.equal(await tokenBalance(page0.atas[0]), 30000);
+    assert.equal(await tokenBalance(page0.atas[1]), 10000);
+
+    await crank(v, 1, true, page1.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.deepEqual(
+      Buffer.from(progress.dayLocksHash),
+      chain(hash0, locks(page1, [200000]))
+    );
+    assert.equal(progress.dayLockedTotal.toString(), "600000");
+    assert.equal(progress.dayLocksCount, 3);
+  });
+
+  it("applies tiered investor shares across tier boundaries", async () => {
+    const tier = (start: number, bps: number) => ({ start: new anchor.BN(start), bps });
+    // 80% on the first 50k claimed, 50% above
+    const v = await setupVault({
+      fund: 1000000,
+      options: { shareTiers: [tier(0, 8000), tier(50000, 5000)] },
+    });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(1000000),
+    ]);
+
+    // Page 0 claims 40k: entirely within the first tier
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(40000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 32000);
+
+    // Page 1 claims 100k: 50k at 80% + 50k at 50%
+    await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 32000 + 65000);
+  });
+
+  it("rejects share tiers that are not monotonic", async () => {
+    const tier = (start: number, bps: number) => ({ start: new anchor.BN(start), bps });
+    for (const shareTiers of [
+      [tier(1000, 8000)],
+      [tier(0, 8000), tier(50000, 5000), tier(50000, 2000)],
+    ]) {
+      await expectError(setupVault({ options: { shareTiers } }), "InvalidShareTiers");
+    }
+    await expectError(setupVault({ options: { shareTiers: [tier(0, 10001)] } }), "InvalidBps");
+  });
+
+  it("rejects the treasury as the creator ATA", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        creatorQuoteAta: v.treasury,
+      }).rpc(),
+      "CreatorAtaIsTreasury"
+    );
+  });
+
+  it("rejects distribution before the settlement delay has passed", async () => {
+    const v = await setupVault({ fund: 1000000, options: { settlementDelaySecs: 3600 } });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+
+    await expectError(
+      crank(v, 1, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "ClaimNotSettled"
+    );
+    assert.equal(await tokenBalance(atas[0]), 40000);
+
+    // Once page 0's claim is an hour old, the day continues
+    await program.methods
+      .rewindMockDay(new anchor.BN(3600))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 80000);
+  });
+
+  it("rejects a zero y0 at initialization", async () => {
+    await expectError(setupVault({ y0: 0 }), "InvalidBaseline");
+  });
+
+  it("carries a creator remainder below min_creator_payout into the next day", async () => {
+    // The investor's 40k share is dusted, leaving a 40k remainder below the 50k minimum
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 100000,
+      options: { minCreatorPayout: new anchor.BN(50000) },
+    });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const closed = (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.creatorPayout.toString(), "0");
+    assert.equal(closed.carryForward.toString(), "40000");
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore);
+
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.carryLamports.toString(), "40000");
+    assert.ok(progress.lastDistributionTs.toNumber() > 0);
+  });
+
+  it("lists the current day's page records in batches", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const pageTotals: string[] = [];
+    for (const [pageIndex, fees] of [[0, 100000], [1, 50000], [2, 20000]]) {
+      const sig = await crank(v, pageIndex, pageIndex === 2, remainingAccounts, {
+        honoraryPosition: await createMockPosition(fees, 0),
+      }).rpc({ commitment: "confirmed" });
+      const page = (await eventsOf(sig)).find((e) => e.name === "investorPayoutPage").data;
+      pageTotals.push(page.pageTotalPayout.toString());
+    }
+    const list = async (offset: number, limit: number) => {
+      const sim = await program.methods
+        .listPageRecords(offset, limit)
+        .accounts({ vault: v.vault, progress: v.progress })
+        .simulate();
+      return sim.events.find((e) => e.name === "pageRecordsListed").data;
+    };
+
+    const first = await list(0, 2);
+    assert.equal(first.total, 3);
+    assert.equal(first.nextOffset, 2);
+    const second = await list(first.nextOffset, 2);
+    assert.equal(second.nextOffset, 3);
+    const records = [...first.records, ...second.records];
+    assert.deepEqual(records.map((r) => r.pageIndex), [0, 1, 2]);
+    assert.deepEqual(records.map((r) => r.distributed.toString()), pageTotals);
+    assert.ok(records.every((r) => r.timestamp.toNumber() > 0));
+
+    const past = await list(10, 2);
+    assert.equal(past.records.length, 0);
+    assert.equal(past.nextOffset, 3);
+  });
+
+  it("rejects a quote mint whose decimals differ from the treasury's", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const nineDecimals = await createMint(
+      provider.connection,
+      payer.payer,
+      payer.publicKey,
+      null,
+      9
+    );
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        poolQuoteMint: nineDecimals,
+      }).rpc(),
+      "QuoteMintMismatch"
+    );
+  });
+
+  it("reports the exhausted daily cap once and pays nothing afterwards", async () => {
+    const v = await setupVault({ fund: 1000000, dailyCap: 30000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(400000),
+    ]);
+    const crankPage = async (pageIndex: number, isLastPage: boolean) =>
+      eventsOf(
+        await crank(v, pageIndex, isLastPage, remainingAccounts, {
+          honoraryPosition: await createMockPosition(20000, 0),
+        }).rpc({ commitment: "confirmed" })
+      );
+
+    // 20k claimed -> 10k investor share per page, 30k cap
+    for (const pageIndex of [0, 1]) {
+      const events = await crankPage(pageIndex, false);
+      assert.notOk(events.find((e) => e.name === "dailyCapExhausted"));
+    }
+    const hit = await crankPage(2, false);
+    const exhausted = hit.find((e) => e.name === "dailyCapExhausted").data;
+    assert.equal(exhausted.pageIndex.toString(), "2");
+    assert.equal(exhausted.dailyCap.toString(), "30000");
+
+    const after = await crankPage(3, true);
+    assert.notOk(after.find((e) => e.name === "dailyCapExhausted"));
+    assert.notOk(after.find((e) => e.name === "investorDusted"));
+    const page = after.find((e) => e.name === "investorPayoutPage").data;
+    assert.equal(page.pageTotalPayout.toString(), "0");
+    assert.equal(await tokenBalance(atas[0]), 15000);
+    assert.equal(await tokenBalance(atas[1]), 15000);
+  });
+
+  it("checks uneven page sizes against the committed investor count", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    await program.methods
+      .commitInvestorCount(3)
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+      })
+      .rpc();
+    const page0 = await pairStreams([
+      await createMockStream(200000),
+      await createMockStream(200000),
+    ]);
+    await crank(v, 0, false, page0.remainingAccounts, {
+      honoraryPosition: await createMockPosition(10000, 0),
+    }).rpc();
+
+    // 2 + 2 investors: one too many for the committed 3
+    const tooMany = await pairStreams([
+      await createMockStream(100000),
+      await createMockStream(100000),
+    ]);
+    await expectError(
+      crank(v, 1, true, tooMany.remainingAccounts, {
+        honoraryPosition: await createMockPosition(10000, 0),
+      }).rpc(),
+      "InvestorSetMismatch"
+    );
+
+    // 2 + 1 matches and closes the day
+    const last = await pairStreams([await createMockStream(100000)]);
+    await crank(v, 1, true, last.remainingAccounts, {
+      honoraryPosition: await createMockPosition(10000, 0),
+    }).rpc();
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.dayLocksCount, 3);
+    assert.ok(progress.lastDistributionTs.toNumber() > 0);
+  });
+
+  it("controls per-investor events with the verbosity level", async () => {
+    const crankWith = async (verbosity: any) => {
+      const v = await setupVault({ fund: 1000000, options: { verbosity } });
+      // One payout and one share below min_payout
+      const { remainingAccounts } = await pairStreams([
+        await createMockStream(400000),
+        await createMockStream(10),
+      ]);
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc({ commitment: "confirmed" });
+      const names = (await eventsOf(sig)).map((e) => e.name);
+      assert.include(names, "investorPayoutPage");
+      assert.include(names, "creatorPayoutDayClosed");
+      return names;
+    };
+
+    const minimal = await crankWith({ minimal: {} });
+    assert.notInclude(minimal, "investorDusted");
+    assert.notInclude(minimal, "investorPaid");
+
+    const standard = await crankWith({ standard: {} });
+    assert.include(standard, "investorDusted");
+    assert.notInclude(standard, "investorPaid");
+
+    const verbose = await crankWith({ verbose: {} });
+    assert.include(verbose, "investorDusted");
+    assert.include(verbose, "investorPaid");
+  });
+
+  it("rejects an investor destination holding a different mint", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const stream = await createMockStream(400000);
+    const wrongMintAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      payer.payer,
+      baseMint,
+      Keypair.generate().publicKey
+    );
+    const error = await expectError(
+      crank(v, 0, true, [
+        { pubkey: stream, isSigner: false, isWritable: false },
+        { pubkey: wrongMintAta.address, isSigner: false, isWritable: true },
+      ], {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "MissingInvestorAta"
+    );
+    const logs: string[] = error.logs ?? [];
+    assert.ok(logs.some((l) => l.includes("expected quote mint")));
+    assert.equal(await tokenBalance(wrongMintAta.address), 0);
+  });
+
+  it("only claims from the fee vault pinned at init", async () => {
+    const feeVaultFor = async (mint: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          payer.payer,
+          mint,
+          Keypair.generate().publicKey
+        )
+      ).address;
+    const feeVault = await feeVaultFor(quoteMint);
+    const v = await setupVault({ fund: 1000000, feeVault });
+    const policy = await program.account.policy.fetch(v.policy);
+    assert.equal(policy.feeVault.toBase58(), feeVault.toBase58());
+
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const expectInvalid = async (extra: any) => {
+      await expectError(crank(v, 0, true, remainingAccounts, extra).rpc(), "InvalidFeeVault");
+    };
+    // A spoofed quote vault and a missing vault are both rejected
+    await expectInvalid({ poolFeeVault: await feeVaultFor(quoteMint) });
+    await expectInvalid({});
+
+    await crank(v, 0, true, remainingAccounts, {
+      poolFeeVault: feeVault,
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+
+    // The vault pinned at init must hold the quote mint
+    await expectError(setupVault({ feeVault: await feeVaultFor(baseMint) }), "InvalidFeeVault");
+  });
+
+  it("ignores treasury growth between pages with explicit claim accounting", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      options: { explicitClaimAccounting: true },
+    });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+
+    // Page 0 claims 100k; the investor's locked share is 40k
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+
+    // Organic growth (e.g. a rebase) lands in the treasury without any claim
+    await mintTo(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      v.treasury,
+      payer.publicKey,
+      500000
+    );
+
+    const sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    const closed = events.find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.totalClaimed.toString(), "100000");
+    assert.equal(closed.totalToInvestors.toString(), "40000");
+    assert.equal(closed.creatorPayout.toString(), "60000");
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore + 60000);
+
+    const growth = events.find((e) => e.name === "treasuryGrowthIgnored");
+    assert.ok(growth, "Expected TreasuryGrowthIgnored");
+    assert.ok(growth.data.amount.toNumber() > 0);
+  });
+
+  it("vests the creator remainder in the CreatorVesting PDA", async () => {
+    // The investor's 40k share is dusted, leaving a 40k creator remainder
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 100000,
+      options: { creatorVestingSecs: 3600 },
+    });
+    const [creatorVesting] = PublicKey.findProgramAddressSync(
+      [Buffer.from("creator_vesting"), v.vault.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openCreatorVesting()
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        creatorAta: creatorQuoteAta,
+        creatorVesting,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "CreatorVestingMissing"
+    );
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+      creatorVesting,
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    const vested = events.find((e) => e.name === "creatorVested").data;
+    assert.equal(vested.amount.toString(), "40000");
+    const closed = events.find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.creatorPayout.toString(), "40000");
+    // Held in the treasury and reserved, not transferred
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.pendingWithdrawalsTotal.toString(), "40000");
+
+    const release = async () =>
+      eventsOf(
+        await program.methods
+          .releaseCreatorVesting()
+          .accounts({
+            vault: v.vault,
+            progress: v.progress,
+            creatorVesting,
+            creatorAta: creatorQuoteAta,
+            investorFeePosOwnerPda: v.ownerPda,
+            programQuoteTreasury: v.treasury,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc({ commitment: "confirmed" })
+      );
+
+    // Once the whole window has passed everything is released to the creator
+    await program.methods
+      .rewindMockCreatorVesting(new anchor.BN(3600))
+      .accounts({ vault: v.vault, creatorVesting })
+      .rpc();
+    const released = (await release()).find((e) => e.name === "creatorVestingReleased").data;
+    assert.equal(released.amount.toString(), "40000");
+    assert.equal(released.stillVesting.toString(), "0");
+    assert.equal(await tokenBalance(creatorQuoteAta), creatorBefore + 40000);
+    const after = await program.account.progress.fetch(v.progress);
+    assert.equal(after.pendingWithdrawalsTotal.toString(), "0");
+
+    await expectError(release(), "NothingPending");
+  });
+
+  it("logs the unpaired stream key on MissingInvestorAccount", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const lonelyStream = await createMockStream(100000);
+    const error = await expectError(
+      crank(v, 0, true, [
+        ...remainingAccounts,
+        { pubkey: lonelyStream, isSigner: false, isWritable: false },
+      ]).rpc(),
+      "MissingInvestorAccount"
+    );
+    const logs: string[] = error.logs ?? [];
+    assert.ok(
+      logs.some(
+        (l) => l.includes(lonelyStream.toBase58()) && l.includes("remaining account 2")
+      ),
+      "Expected the offending stream key in the logs"
+    );
+  });
+
+  it("accumulates per-stream totals in stream ledgers across days", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000, options: { streamLedger: true } });
+    const streams = [await createMockStream(400000), await createMockStream(200000)];
+    const { atas } = await pairStreams(streams);
+    const remainingAccounts = [];
+    const ledgers: PublicKey[] = [];
+    for (const [i, stream] of streams.entries()) {
+      const [ledger] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stream_ledger"), v.vault.toBuffer(), stream.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .openStreamLedger()
+        .accounts({
+          payer: payer.publicKey,
+          vault: v.vault,
+          stream,
+          streamLedger: ledger,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      ledgers.push(ledger);
+      remainingAccounts.push(
+        { pubkey: stream, isSigner: false, isWritable: false },
+        { pubkey: atas[i], isSigner: false, isWritable: true },
+        { pubkey: ledger, isSigner: false, isWritable: true }
+      );
+    }
+
+    for (let day = 0; day < 2; day++) {
+      if (day > 0) {
+        await program.methods
+          .rewindMockDay(new anchor.BN(DAY))
+          .accounts({ vault: v.vault, progress: v.progress })
+          .rpc();
+      }
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+    }
+
+    for (const [i, ledger] of ledgers.entries()) {
+      const entry = await program.account.streamLedger.fetch(ledger);
+      assert.equal(entry.stream.toBase58(), streams[i].toBase58());
+      assert.equal(entry.daysPaid, 2);
+      assert.equal(entry.totalDistributed.toNumber(), await tokenBalance(atas[i]));
+    }
+    // 400k vs 200k locked: the first stream earned twice as much (floor rounding aside)
+    const [first, second] = await Promise.all(
+      ledgers.map((l) => program.account.streamLedger.fetch(l))
+    );
+    assert.approximately(first.totalDistributed.toNumber(), 2 * second.totalDistributed.toNumber(), 2);
+  });
+
+  it("refuses to start a day while the snapshot exceeds the treasury balance", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const setSnapshot = (amount: number) =>
+      program.methods
+        .setMockTreasurySnapshot(new anchor.BN(amount))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+
+    await setSnapshot(1000001);
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "AccountingImbalance"
+    );
+
+    // Once reconciled the day proceeds, and closes on the post-payout balance
+    await setSnapshot(1000000);
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.treasurySnapshot.toNumber(), await tokenBalance(v.treasury));
+  });
+
+  it("settles the creator per page with the same totals as the lump sum", async () => {
+    const runDay = async (creatorPerPage: boolean) => {
+      const v = await setupVault({
+        fund: 1000000,
+        options: { explicitClaimAccounting: true, creatorPerPage },
+      });
+      const { remainingAccounts } = await pairStreams([
+        await createMockStream(400000),
+        await createMockStream(150000),
+      ]);
+      const creatorBefore = await tokenBalance(creatorQuoteAta);
+      const pagePayouts: number[] = [];
+      let closed: any;
+      for (const [pageIndex, fees] of [[0, 100000], [1, 50000], [2, 20001]]) {
+        const sig = await crank(v, pageIndex, pageIndex === 2, remainingAccounts, {
+          honoraryPosition: await createMockPosition(fees, 0),
+        }).rpc({ commitment: "confirmed" });
+        const events = await eventsOf(sig);
+        const page = events.find((e) => e.name === "creatorPagePayout");
+        if (page) pagePayouts.push(page.data.amount.toNumber());
+        closed = closed ?? events.find((e) => e.name === "creatorPayoutDayClosed")?.data;
+      }
+      return {
+        pagePayouts,
+        closed,
+        received: (await tokenBalance(creatorQuoteAta)) - creatorBefore,
+      };
+    };
+
+    const lump = await runDay(false);
+    const perPage = await runDay(true);
+
+    assert.deepEqual(lump.pagePayouts, []);
+    assert.equal(perPage.pagePayouts.length, 2);
+    assert.ok(perPage.pagePayouts.every((amount) => amount > 0));
+    // The last page reconciles rounding and carry, so the day adds up the same
+    assert.equal(perPage.received, lump.received);
+    assert.equal(perPage.closed.creatorPayout.toString(), lump.closed.creatorPayout.toString());
+    assert.equal(perPage.closed.totalToInvestors.toString(), lump.closed.totalToInvestors.toString());
+    assert.ok(perPage.received > perPage.pagePayouts[0] + perPage.pagePayouts[1]);
+  });
+
+  it("leaves clean per-day state after every kind of day transition", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+    const runPage = async (pageIndex: number, isLastPage: boolean) =>
+      crank(v, pageIndex, isLastPage, remainingAccounts, {
+        honoraryPosition: await createMockPosition(10000, 0),
+      }).rpc();
+    const cancelDay = () =>
+      program.methods
+        .cancelDay()
+        .accounts({
+          authority: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          investorFeePosOwnerPda: v.ownerPda,
+          programQuoteTreasury: v.treasury,
+        })
+        .rpc({ commitment: "confirmed" });
+    const rewind = () =>
+      program.methods
+        .rewindMockDay(new anchor.BN(DAY))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+    const assertFreshDay = async (expectedRecords: number) => {
+      const p = await program.account.progress.fetch(v.progress);
+      assert.equal(p.pageRecords.length, expectedRecords);
+      assert.equal(p.cursor.toNumber(), 0);
+      assert.equal(p.dayLocksCount, expectedRecords);
+      assert.equal(p.creatorPaidToday.toNumber(), 0);
+      return p;
+    };
+
+    // Nothing to cancel before the first day
+    await expectError(cancelDay(), "DayNotOpen");
+
+    // Normal close, then a new day: only the new page 0 is on record
+    await runPage(0, false);
+    await runPage(1, true);
+    await rewind();
+    await runPage(0, false);
+    const afterClose = await assertFreshDay(1);
+    assert.equal(afterClose.claimedToday.toNumber(), 10000);
+
+    // Cancel mid-day: everything per-day is cleared, and no more pages run today
+    await runPage(1, false);
+    const cancelled = (await eventsOf(await cancelDay())).find((e) => e.name === "dayCancelled").data;
+    assert.equal(cancelled.pagesProcessed, 2);
+    const afterCancel = await assertFreshDay(0);
+    assert.equal(afterCancel.cumulativeDistributedToday.toNumber(), 0);
+    assert.equal(afterCancel.claimedToday.toNumber(), 0);
+    assert.equal(afterCancel.treasurySnapshot.toNumber(), await tokenBalance(v.treasury));
+    assert.ok(afterCancel.lastDistributionTs.toNumber() >= afterCancel.dayStartTs.toNumber());
+    await expectError(runPage(1, true), "DayNotOpen");
+
+    // The next day starts from the clean state
+    await rewind();
+    await runPage(0, true);
+    await assertFreshDay(1);
+  });
+
+  it("only initializes positions on allowlisted quote mints once the program config exists", async () => {
+    const [programConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    );
+    const setAllowlist = (mints: PublicKey[], admin: Keypair = payer.payer) =>
+      program.methods
+        .setQuoteMintAllowlist(mints)
+        .accounts({ admin: admin.publicKey, programConfig })
+        .signers(admin === payer.payer ? [] : [admin])
+        .rpc();
+
+    await program.methods
+      .initProgramConfig([quoteMint])
+      .accounts({ admin: payer.publicKey, programConfig })
+      .rpc();
+    try {
+      const config = await program.account.programConfig.fetch(programConfig);
+      assert.ok(config.admin.equals(payer.publicKey));
+      assert.equal(config.quoteMints.length, 1);
+
+      // Approved mint
+      await setupVault();
+
+      // Rejected mint
+      await setAllowlist([Keypair.generate().publicKey]);
+      await expectError(setupVault(), "QuoteMintNotAllowed");
+
+      // Only the admin manages the list, and duplicates are refused
+      await expectError(setAllowlist([quoteMint], Keypair.generate()), "Unauthorized");
+      await expectError(setAllowlist([quoteMint, quoteMint]), "InvalidQuoteMintAllowlist");
+    } finally {
+      // An empty allowlist admits every mint again for the remaining tests
+      await setAllowlist([]);
+    }
+    await setupVault();
+  });
+
+  it("pays a cranker who is also an investor only their investor share", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const keeper = Keypair.generate();
+    const keeperAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        quoteMint,
+        keeper.publicKey
+      )
+    ).address;
+    const keeperStream = await createMockStream(400000);
+    const other = await pairStreams([await createMockStream(400000)]);
+    const remainingAccounts = [
+      { pubkey: keeperStream, isSigner: false, isWritable: false },
+      { pubkey: keeperAta, isSigner: false, isWritable: true },
+      ...other.remainingAccounts,
+    ];
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      cranker: keeper.publicKey,
+      honoraryPosition: await createMockPosition(20000, 0),
+    })
+      .signers([keeper])
+      .rpc({ commitment: "confirmed" });
+
+    // Equal locks -> equal shares of the 10k investor allocation, whoever cranks
+    const page = (await eventsOf(sig)).find((e) => e.name === "investorPayoutPage").data;
+    assert.equal(await tokenBalance(keeperAta), 5000);
+    assert.equal(await tokenBalance(other.atas[0]), 5000);
+    assert.equal(page.pageTotalPayout.toNumber(), 10000);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.cumulativeDistributedToday.toNumber(), 10000);
+  });
+
+  it("rejects or skips trailing program and sysvar accounts per skip_trailing_accounts", async () => {
+    const junk = [
+      { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ];
+    // By default trailing junk is its own error, even when it makes the count odd
+    const strict = await setupVault({ fund: 1000000 });
+    const strictPairs = await pairStreams([await createMockStream(400000)]);
+    for (const trailing of [junk, junk.slice(0, 1)]) {
+      await expectError(
+        crank(strict, 0, true, [...strictPairs.remainingAccounts, ...trailing]).rpc(),
+        "UnexpectedRemainingAccount"
+      );
+    }
+
+    // With the flag the junk is ignored and the pairs are paid as usual
+    const lenient = await setupVault({ fund: 1000000, options: { skipTrailingAccounts: true } });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    await crank(lenient, 0, true, [...remainingAccounts, ...junk], {
+      honoraryPosition: await createMockPosition(20000, 0),
+    }).rpc();
+    // 400k of the 1M baseline locked -> 40% of the 20k claim
+    assert.equal(await tokenBalance(atas[0]), 8000);
+
+    // A sysvar in a stream slot is never skipped, and a lone stream is still a missing ATA
+    const misplaced = await setupVault({ fund: 1000000, options: { skipTrailingAccounts: true } });
+    await expectError(
+      crank(misplaced, 0, true, [junk[0], ...remainingAccounts]).rpc(),
+      "UnexpectedRemainingAccount"
+    );
+    await expectError(
+      crank(misplaced, 0, true, [...remainingAccounts, remainingAccounts[0], ...junk]).rpc(),
+      "MissingInvestorAccount"
+    );
+  });
+
+  it("splits a single-page day exactly between investors and the creator", async () => {
+    const DAY = 86400;
+    // 410k of the 1M baseline locked -> 41k of a 100k claim: 40k paid, 1k dusted (< minPayout)
+    const singlePageDay = async (v: Awaited<ReturnType<typeof setupVault>>, remainingAccounts: any[]) => {
+      const creatorBefore = await tokenBalance(creatorQuoteAta);
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc({ commitment: "confirmed" });
+      const closed = (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data;
+      return { closed, creatorReceived: (await tokenBalance(creatorQuoteAta)) - creatorBefore };
+    };
+    const streams = async () =>
+      pairStreams([await createMockStream(400000), await createMockStream(10000)]);
+
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 5000,
+      options: { explicitClaimAccounting: true },
+    });
+    const { remainingAccounts, atas } = await streams();
+    const { closed, creatorReceived } = await singlePageDay(v, remainingAccounts);
+    assert.equal(closed.totalClaimed.toNumber(), 100000);
+    assert.equal(closed.totalToInvestors.toNumber(), 40000);
+    assert.equal(
+      closed.totalClaimed.toNumber(),
+      closed.totalToInvestors.toNumber() + closed.creatorPayout.toNumber()
+    );
+    assert.equal(creatorReceived, 60000);
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(await tokenBalance(atas[1]), 0);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.carryLamports.toNumber(), 0);
+    assert.equal(progress.dustToday.toNumber(), 1000);
+    assert.equal(progress.cursor.toNumber(), 0);
+    assert.equal(progress.pageRecords.length, 1);
+    assert.equal(progress.creatorPending.toNumber(), 0);
+    assert.ok(progress.lastDistributionTs.toNumber() > 0);
+    assert.equal(progress.treasurySnapshot.toNumber(), await tokenBalance(v.treasury));
+
+    // A carried remainder is added once on the next single-page day, the dust not again
+    const carried = await setupVault({
+      fund: 1000000,
+      minPayout: 5000,
+      options: { explicitClaimAccounting: true, minCreatorPayout: new anchor.BN(70000) },
+    });
+    const carriedPairs = await streams();
+    const day1 = await singlePageDay(carried, carriedPairs.remainingAccounts);
+    assert.equal(day1.creatorReceived, 0);
+    assert.equal(
+      (await program.account.progress.fetch(carried.progress)).carryLamports.toNumber(),
+      60000
+    );
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: carried.vault, progress: carried.progress })
+      .rpc();
+    const day2 = await singlePageDay(carried, carriedPairs.remainingAccounts);
+    assert.equal(day2.creatorReceived, 120000);
+    assert.equal(day2.closed.creatorPayout.toNumber(), 120000);
+    assert.equal(
+      (await program.account.progress.fetch(carried.progress)).carryLamports.toNumber(),
+      0
+    );
+    // Two days of 100k claims: 80k to investors, the rest (dust included) to the creator
+    assert.equal(await tokenBalance(carriedPairs.atas[0]), 80000);
+  });
+
+  it("reads every investor's lock from one bulk lock index", async () => {
+    const { streams, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(100000),
+    ]);
+    const entries = [
+      { stream: streams[0], investorAta: atas[0], lockedAmount: new anchor.BN(400000) },
+      { stream: streams[1], investorAta: atas[1], lockedAmount: new anchor.BN(100000) },
+    ];
+    const lockIndex = Keypair.generate();
+    const space = 8 + 4 + entries.length * 72;
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: lockIndex.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+          space,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, lockIndex]
+    );
+    await program.methods
+      .writeMockLockIndex(entries)
+      .accounts({ lockIndex: lockIndex.publicKey })
+      .rpc();
+    const account = (pubkey: PublicKey, isWritable = false) => ({ pubkey, isSigner: false, isWritable });
+    // Index first, then the investor ATAs alone: 500k locked -> 10k of a 20k claim, pro rata
+    const v = await setupVault({ fund: 1000000, options: { bulkLockIndex: true } });
+    const sig = await crank(v, 0, true, [account(lockIndex.publicKey), account(atas[0], true), account(atas[1], true)], {
+      honoraryPosition: await createMockPosition(20000, 0),
+    }).rpc({ commitment: "confirmed" });
+    assert.equal(await tokenBalance(atas[0]), 8000);
+    assert.equal(await tokenBalance(atas[1]), 2000);
+    const snapshot = (await eventsOf(sig)).find((e) => e.name === "locksSnapshot").data;
+    assert.equal(snapshot.pageLockedTotal.toString(), "500000");
+
+    // Every ATA must be listed, and the first account must be a lock index
+    const strayAta = (await pairStreams([streams[0]])).atas[0];
+    const other = await setupVault({ fund: 1000000, options: { bulkLockIndex: true } });
+    await expectError(
+      crank(other, 0, true, [account(lockIndex.publicKey), account(strayAta, true)]).rpc(),
+      "InvestorNotInLockIndex"
+    );
+    await expectError(
+      crank(other, 0, true, [account(streams[0]), account(atas[0], true)]).rpc(),
+      "InvalidLockIndex"
+    );
+
+    // Ledgers are keyed by stream slots, which bulk mode doesn't have
+    await expectError(
+      setupVault({ options: { bulkLockIndex: true, streamLedger: true } }),
+      "IncompatibleOptions"
+    );
+  });
+
+  it("reports the quote mint decimals alongside raw payout amounts", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    const { decimals } = await getMint(provider.connection, quoteMint);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(20000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    const page = events.find((e) => e.name === "investorPayoutPage").data;
+    const closed = events.find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(decimals, 6);
+    assert.equal(page.quoteDecimals, decimals);
+    assert.equal(closed.quoteDecimals, decimals);
+  });
+
+  it("lets the open day finish while new days are paused", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    const setNewDaysPaused = (paused: boolean) =>
+      program.methods
+        .setNewDaysPaused(paused)
+        .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+        .rpc();
+    const page = async (pageIndex: number, isLastPage: boolean) =>
+      crank(v, pageIndex, isLastPage, remainingAccounts, {
+        honoraryPosition: await createMockPosition(10000, 0),
+      }).rpc();
+    const rewindDay = () =>
+      program.methods
+        .rewindMockDay(new anchor.BN(86400))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+
+    await page(0, false);
+    await setNewDaysPaused(true);
+    assert.isTrue((await program.account.policy.fetch(v.policy)).newDaysPaused);
+
+    // The open day keeps paying and closes as usual
+    await page(1, false);
+    await page(2, true);
+    assert.equal(await tokenBalance(atas[0]), 12000);
+    assert.ok((await program.account.progress.fetch(v.progress)).lastDistributionTs.toNumber() > 0);
+
+    // But the next day can't start until new days are resumed
+    await rewindDay();
+    await expectError(page(0, true), "NewDaysPaused");
+    await setNewDaysPaused(false);
+    await page(0, true);
+    assert.equal(await tokenBalance(atas[0]), 16000);
+  });
+
+  it("flushes the carry to the configured destination", async () => {
+    // 410k of the 1M baseline locked -> 41k of each 100k claim: 40k paid, 1k dusted (< minPayout)
+    const setup = async (options: any) => {
+      const v = await setupVault({
+        fund: 1000000,
+        minPayout: 5000,
+        options: { explicitClaimAccounting: true, ...options },
+      });
+      const pairs = await pairStreams([await createMockStream(400000), await createMockStream(10000)]);
+      return { v, ...pairs };
+    };
+    const singlePageDay = async (
+      v: Awaited<ReturnType<typeof setupVault>>,
+      remainingAccounts: any[],
+      extraAccounts: any = {}
+    ) => {
+      const creatorBefore = await tokenBalance(creatorQuoteAta);
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+        ...extraAccounts,
+      }).rpc({ commitment: "confirmed" });
+      const flushed = (await eventsOf(sig)).filter((e) => e.name === "carryFlushed").map((e) => e.data);
+      return { flushed, creator: (await tokenBalance(creatorQuoteAta)) - creatorBefore };
+    };
+    const carryOf = async (v: Awaited<ReturnType<typeof setupVault>>) =>
+      (await program.account.progress.fetch(v.progress)).carryLamports.toNumber();
+    // Creator (default): the dust is part of the remainder
+    const toCreator = await setup({});
+    const creatorDay = await singlePageDay(toCreator.v, toCreator.remainingAccounts);
+    assert.equal(creatorDay.creator, 60000);
+    assert.equal(creatorDay.flushed.length, 1);
+    assert.ok(creatorDay.flushed[0].destination.creator);
+    assert.equal(creatorDay.flushed[0].amount.toNumber(), 1000);
+    assert.equal(await carryOf(toCreator.v), 0);
+
+    // Investors next day: the creator doesn't get the dust; the next day's investors share it
+    const toInvestors = await setup({ carryDestination: { investorsNextDay: {} } });
+    const day1 = await singlePageDay(toInvestors.v, toInvestors.remainingAccounts);
+    assert.equal(day1.creator, 59000);
+    assert.equal(day1.flushed.length, 0);
+    assert.equal(await carryOf(toInvestors.v), 1000);
+    await program.methods
+      .rewindMockDay(new anchor.BN(86400))
+      .accounts({ vault: toInvestors.v.vault, progress: toInvestors.v.progress })
+      .rpc();
+    const day2 = await singlePageDay(toInvestors.v, toInvestors.remainingAccounts);
+    assert.equal(day2.flushed.length, 1);
+    assert.ok(day2.flushed[0].destination.investorsNextDay);
+    assert.equal(day2.flushed[0].amount.toNumber(), 1000);
+    // 42k pool: 40975 paid, 1025 dusted and carried again
+    assert.equal(await tokenBalance(toInvestors.atas[0]), 40000 + 40975);
+    assert.equal(day2.creator, 59000);
+    assert.equal(await carryOf(toInvestors.v), 1025);
+
+    // Dust recipient: the pinned account receives the carry on the last page
+    const recipientAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer.payer, quoteMint, Keypair.generate().publicKey)
+    ).address;
+    const toRecipient = await setup({ carryDestination: { dustRecipient: {} }, dustRecipient: recipientAta });
+    await expectError(
+      singlePageDay(toRecipient.v, toRecipient.remainingAccounts),
+      "InvalidDustRecipient"
+    );
+    const recipientDay = await singlePageDay(toRecipient.v, toRecipient.remainingAccounts, {
+      dustRecipientAta: recipientAta,
+    });
+    assert.equal(recipientDay.creator, 59000);
+    assert.ok(recipientDay.flushed[0].destination.dustRecipient);
+    assert.equal(await tokenBalance(recipientAta), 1000);
+    assert.equal(await carryOf(toRecipient.v), 0);
+
+    // Misconfigurations are rejected at init
+    await expectError(setupVault({ options: { carryDestination: { dustRecipient: {} } } }), "InvalidDustRecipient");
+    await expectError(
+      setupVault({
+        options: { carryDestination: { investorsNextDay: {} }, minCreatorPayout: new anchor.BN(1) },
+      }),
+      "IncompatibleOptions"
+    );
+  });
+
+  it("pays a single investor the same through the one-pair fast path", async () => {
+    const runPage = async (locks: number[]) => {
+      const v = await setupVault({ fund: 1000000 });
+      const streams = [];
+      for (const locked of locks) streams.push(await createMockStream(locked));
+      const { remainingAccounts, atas } = await pairStreams(streams);
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(33333, 0),
+      }).rpc({ commitment: "confirmed" });
+      const events = await eventsOf(sig);
+      return {
+        paid: await tokenBalance(atas[0]),
+        page: events.find((e) => e.name === "investorPayoutPage").data,
+        report: events.find((e) => e.name === "pageComputeReport").data,
+        progress: await program.account.progress.fetch(v.progress),
+      };
+    };
+
+    // One pair vs. the same investor next to a zero-locked one (general pro-rata path)
+    const single = await runPage([400000]);
+    const general = await runPage([400000, 0]);
+    assert.equal(single.paid, 13333);
+    assert.equal(single.paid, general.paid);
+    assert.equal(single.page.pageTotalPayout.toString(), general.page.pageTotalPayout.toString());
+    assert.equal(single.page.carryAfterPage.toString(), general.page.carryAfterPage.toString());
+    assert.equal(single.progress.dustToday.toString(), general.progress.dustToday.toString());
+    assert.equal(single.report.investors, 1);
+    assert.equal(general.report.investors, 2);
+  });
+
+  it("emits NoDailyCapConfigured when a vault is initialized without a cap", async () => {
+    const uncapped = await setupVault({ dailyCap: null });
+    const notice = (await eventsOf(uncapped.initSig)).find(
+      (e) => e.name === "noDailyCapConfigured"
+    );
+    assert.ok(notice, "an uncapped init should emit NoDailyCapConfigured");
+    assert.equal(notice.data.vault.toBase58(), uncapped.vault.toBase58());
+    assert.isNull((await program.account.policy.fetch(uncapped.policy)).dailyCap);
+
+    const capped = await setupVault({ dailyCap: 500000 });
+    const events = await eventsOf(capped.initSig);
+    assert.ok(events.find((e) => e.name === "honoraryPositionInitialized"));
+    assert.notOk(events.find((e) => e.name === "noDailyCapConfigured"));
+  });
+
+  it("lets an investor claim their own share with investor_claim", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const investor = Keypair.generate();
+    const ownAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer.payer, quoteMint, investor.publicKey)
+    ).address;
+    const stream = await createMockStream(400000);
+    const own = [
+      { pubkey: stream, isSigner: false, isWritable: false },
+      { pubkey: ownAta, isSigner: false, isWritable: true },
+    ];
+    const investorClaim = (remainingAccounts: any[], honoraryPosition = Keypair.generate().publicKey) =>
+      program.methods
+        .investorClaim(new anchor.BN(0), false, null, null)
+        .accounts({
+          cranker: investor.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          investorFeePosOwnerPda: v.ownerPda,
+          honoraryPosition,
+          programQuoteTreasury: v.treasury,
+          creatorQuoteAta: creatorQuoteAta,
+          pool: Keypair.generate().publicKey,
+          poolQuoteMint: quoteMint,
+          poolBaseMint: baseMint,
+          cpAmmProgram: Keypair.generate().publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([investor]);
+
+    // Someone else's ATA, or more than the signer's own pair, is refused
+    const { remainingAccounts: other } = await pairStreams([stream]);
+    for (const accounts of [other, [...own, ...other]]) {
+      try {
+        await investorClaim(accounts).rpc();
+        assert.fail("investor_claim should only pay the signer's own ATA");
+      } catch (err) {
+        assert.include(err.toString(), "NotOwnInvestorAccount");
+      }
+    }
+
+    await investorClaim(own, await createMockPosition(100000, 0)).rpc();
+    // 400k of y0 = 1M locked: 40% of the 100k claim, the rest left for the creator
+    assert.equal(await tokenBalance(ownAta), 40000);
+    assert.equal((await program.account.progress.fetch(v.progress)).investorsPaidToday, 1);
+  });
+
+  it("rejects a page built for a prior day once the next day has started", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    const dayTs = async () => (await program.account.progress.fetch(v.progress)).dayStartTs;
+
+    // Day 1, each page pinned to the day its keeper observed
+    const beforeDay1 = await dayTs();
+    await crank(v, 0, false, remainingAccounts, {}, null, beforeDay1).rpc();
+    const day1 = await dayTs();
+    await crank(v, 1, true, remainingAccounts, {}, null, day1).rpc();
+
+    await program.methods
+      .rewindMockDay(new anchor.BN(86400))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 0, false, remainingAccounts, {}, null, await dayTs()).rpc();
+    const day2 = await dayTs();
+    assert.notEqual(day2.toString(), day1.toString());
+
+    // Day 1's pages, re-submitted against day 2, carry a day that has moved on
+    for (const [pageIndex, observed] of [[0, beforeDay1], [1, day1]] as const) {
+      try {
+        await crank(v, pageIndex, pageIndex === 1, remainingAccounts, {}, null, observed).rpc();
+        assert.fail("a prior day's page should not land on the new day");
+      } catch (err) {
+        assert.include(err.toString(), "StaleDay");
+      }
+    }
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.cursor.toNumber(), 0);
+    assert.equal(progress.pageRecords.length, 1);
+    assert.equal(progress.pageRecords[0].dayTs.toString(), day2.toString());
+  });
+
+  it("takes the investor share from a share oracle, clamped to the policy bounds", async () => {
+    const oracle = Keypair.generate();
+    const accountSize = 10;
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: oracle.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(accountSize),
+          space: accountSize,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, oracle]
+    );
+    const setShare = (bps: number) =>
+      program.methods.writeMockShareOracle(bps).accounts({ oracle: oracle.publicKey }).rpc();
+
+    const v = await setupVault({
+      fund: 1000000,
+      options: {
+        shareOracle: oracle.publicKey,
+        shareOracleProgram: program.programId,
+        oracleMinBps: 1000,
+        oracleMaxBps: 3000,
+      },
+    });
+    // Fully locked, so the share alone sets the investor portion
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(1000000)]);
+    const closeDay = async () =>
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+        shareOracle: oracle.publicKey,
+      }).rpc();
+
+    try {
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      assert.fail("a crank without the oracle should fail");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidShareOracle");
+    }
+
+    await setShare(2000);
+    await closeDay();
+    assert.equal(await tokenBalance(atas[0]), 20000);
+
+    // Readings outside the bounds are clamped
+    await program.methods
+      .rewindMockDay(new anchor.BN(86400))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await setShare(9000);
+    await closeDay();
+    assert.equal(await tokenBalance(atas[0]), 20000 + 30000);
+  });
+
+  it("initializes the policy and creates the position in one instruction", async () => {
+    const initAndCreate = (position: Keypair, y0 = 1000000) => {
+      const vaultKp = Keypair.generate();
+      const [policy] = PublicKey.findProgramAddressSync(
+        [Buffer.from("policy"), vaultKp.publicKey.toBuffer()],
+        program.programId
+      );
+      const [progress] = PublicKey.findProgramAddressSync(
+        [Buffer.from("progress"), vaultKp.publicKey.toBuffer()],
+        program.programId
+      );
+      const [ownerPda, bump] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+        program.programId
+      );
+      const call = program.methods
+        .initializeAndCreatePosition(
+          bump,
+          new anchor.BN(y0),
+          5000,
+          new anchor.BN(1000000),
+          new anchor.BN(1000),
+          new anchor.BN(100),
+          defaultPolicyOptions()
+        )
+        .accounts({
+          initializer: payer.publicKey,
+          vault: vaultKp.publicKey,
+          policy,
+          progress,
+          investorFeePosOwnerPda: ownerPda,
+          honoraryPosition: position.publicKey,
+          programQuoteTreasury: getAssociatedTokenAddressSync(quoteMint, ownerPda, true),
+          pool: Keypair.generate().publicKey,
+          poolQuoteMint: quoteMint,
+          poolBaseMint: baseMint,
+          poolFeeVault: null,
+          cpAmmProgram: Keypair.generate().publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .signers([payer.payer, position]);
+      return { call, policy, progress };
+    };
+
+    const position = Keypair.generate();
+    const { call, policy, progress } = initAndCreate(position);
+    await call.rpc();
+    const positionInfo = await provider.connection.getAccountInfo(position.publicKey);
+    assert.ok(positionInfo, "the position should exist");
+    assert.equal(positionInfo.owner.toBase58(), program.programId.toBase58());
+    assert.equal(positionInfo.data.length, 16);
+    assert.ok(await program.account.policy.fetch(policy));
+    assert.ok(await program.account.progress.fetch(progress));
+
+    // A failing init leaves no position behind...
+    const rejected = Keypair.generate();
+    try {
+      await initAndCreate(rejected, 0).call.rpc();
+      assert.fail("y0 = 0 should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidBaseline");
+    }
+    assert.isNull(await provider.connection.getAccountInfo(rejected.publicKey));
+
+    // ...and a failing position creation leaves no policy behind
+    const taken = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: taken.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(16),
+          space: 16,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, taken]
+    );
+    const attempt = initAndCreate(taken);
+    let created = true;
+    try {
+      await attempt.call.rpc();
+    } catch (err) {
+      created = false; // the account is already in use
+    }
+    assert.isFalse(created, "an existing position account should not be recreated");
+    assert.isNull(await provider.connection.getAccountInfo(attempt.policy));
+    assert.isNull(await provider.connection.getAccountInfo(attempt.progress));
+  });
+
+  it("refuses to start a day with fewer investors than min_investors_for_day", async () => {
+    const v = await setupVault({ fund: 1000000, options: { minInvestorsForDay: 3 } });
+    const commit = (total: number) =>
+      program.methods
+        .commitInvestorCount(total)
+        .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+        .rpc();
+    const { remainingAccounts } = await pairStreams([await createMockStream(10)]);
+
+    // Nothing committed, then too few committed: a lone tiny investor can't open the day
+    for (const committed of [null, 2]) {
+      if (committed !== null) await commit(committed);
+      try {
+        await crank(v, 0, true, remainingAccounts).rpc();
+        assert.fail("page 0 should require min_investors_for_day");
+      } catch (err) {
+        assert.include(err.toString(), "TooFewInvestors");
+      }
+    }
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.lastDistributionTs.toNumber(), 0);
+    assert.equal(progress.pageRecords.length, 0);
+
+    // With enough committed the day opens, but can't close over a single investor
+    await commit(3);
+    try {
+      await crank(v, 0, true, remainingAccounts).rpc();
+      assert.fail("the committed count should still gate the close");
+    } catch (err) {
+      assert.include(err.toString(), "InvestorCountMismatch");
+    }
+    await crank(v, 0, false, remainingAccounts).rpc();
+    assert.equal((await program.account.progress.fetch(v.progress)).dayLocksCount, 1);
+  });
+
+  it("pays the day from its escrow so direct treasury deposits stay out of it", async () => {
+    const v = await setupVault({ fund: 1000000, options: { dayEscrow: true } });
+    const [dayEscrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("day_escrow"), v.vault.toBuffer()],
+      program.programId
+    );
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    try {
+      await crank(v, 0, false, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      assert.fail("an escrow vault should require the day escrow");
+    } catch (err) {
+      assert.include(err.toString(), "DayEscrowMissing");
+    }
+
+    await program.methods
+      .createDayEscrow()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        investorFeePosOwnerPda: v.ownerPda,
+        dayEscrow,
+        poolQuoteMint: quoteMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Page 0 moves its 100k claim into the escrow and pays the investor's 40% from there
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+      dayEscrow,
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(await tokenBalance(dayEscrow), 60000);
+
+    // Someone deposits straight into the treasury mid-day
+    await mintTo(provider.connection, payer.payer, quoteMint, v.treasury, payer.publicKey, 55555);
+
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+      dayEscrow,
+    }).rpc({ commitment: "confirmed" });
+    // The creator gets the day's claim less the investor payout, not the deposit
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 60000);
+    const closed = (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.totalClaimed.toString(), "100000");
+    const swept = (await eventsOf(sig)).find((e) => e.name === "dayEscrowSwept").data;
+    assert.equal(swept.amount.toString(), "0");
+    assert.equal(await tokenBalance(dayEscrow), 0);
+    assert.equal(await tokenBalance(v.treasury), 1000000 - 100000 + 55555);
+  });
+
+  it("reads the treasury after the claim transfer lands", async () => {
+    // A fee vault held by the owner PDA makes the local claim a real transfer
+    const vaultKp = Keypair.generate();
+    const [ownerPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+      program.programId
+    );
+    const feeVault = await createAccount(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      ownerPda,
+      Keypair.generate()
+    );
+    await mintTo(provider.connection, payer.payer, quoteMint, feeVault, payer.publicKey, 100000);
+    const v = await setupVault({ vaultKp, feeVault });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    await crank(v, 0, true, remainingAccounts, {
+      poolFeeVault: feeVault,
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+
+    assert.equal(await tokenBalance(feeVault), 0);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.claimedToday.toNumber(), 100000);
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(progress.treasurySnapshot.toNumber(), await tokenBalance(v.treasury));
+  });
+
+  it("takes the balance-delta remainder after the last page's payouts", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+
+    // Page 0 claims 100k (snapshot 1.1M) and pays the investor 40k (balance 960k)
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    // Unclaimed growth lands mid-day: 1.46M
+    await mintTo(provider.connection, payer.payer, quoteMint, v.treasury, payer.publicKey, 500000);
+
+    // The last page claims another 100k (snapshot 1.2M) and pays 40k more (balance 1.42M)
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    // Remainder = (1.42M - 1.2M) - 80k distributed; the entry balance would give 40k more
+    const closed = (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.totalToInvestors.toString(), "80000");
+    assert.equal(closed.creatorPayout.toString(), "140000");
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 140000);
+    assert.equal(await tokenBalance(v.treasury), 1420000 - 140000);
+  });
+
+  it("reports a treasury balance diverging from the day's accounting", async () => {
+    // Claims paid by a real transfer (fee vault held by the owner PDA) keep the books exact
+    const runDay = async (mode: any, tolerance: number) => {
+      const vaultKp = Keypair.generate();
+      const [ownerPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+        program.programId
+      );
+      const feeVault = await createAccount(provider.connection, payer.payer, quoteMint, ownerPda, Keypair.generate());
+      await mintTo(provider.connection, payer.payer, quoteMint, feeVault, payer.publicKey, 100000);
+      const v = await setupVault({
+        vaultKp,
+        feeVault,
+        options: { divergenceMode: mode, divergenceTolerance: new anchor.BN(tolerance) },
+      });
+      const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+      await crank(v, 0, false, remainingAccounts, {
+        poolFeeVault: feeVault,
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      // 7 units reach the treasury outside any booked path
+      await mintTo(provider.connection, payer.payer, quoteMint, v.treasury, payer.publicKey, 7);
+      const sig = await crank(v, 1, true, remainingAccounts, {
+        poolFeeVault: feeVault,
+        honoraryPosition: await createMockPosition(0, 0),
+      }).rpc({ commitment: "confirmed" });
+      return (await eventsOf(sig)).find((e) => e.name === "accountingDivergence");
+    };
+
+    const divergence = await runDay({ emit: {} }, 5);
+    assert.ok(divergence, "Expected AccountingDivergence");
+    assert.equal(divergence.data.expected.toString(), "60000");
+    assert.equal(divergence.data.actual.toString(), "60007");
+    assert.equal(divergence.data.divergence.toString(), "7");
+
+    assert.notOk(await runDay({ emit: {} }, 10));
+    assert.notOk(await runDay({ off: {} }, 0));
+
+    await expectError(runDay({ fail: {} }, 5), "AccountingDivergence");
+  });
+
+  it("weights investor payouts by the selected stream basis", async () => {
+    const farFuture = Math.floor(Date.now() / 1000) + 1_000_000_000;
+    const streams = [
+      await createMockStream(300000, 1, 2), // fully vested
+      await createMockStream(100000), // no schedule: fully locked
+      await createMockStream(100000, farFuture, farFuture + 1), // not started: fully locked
+    ];
+    // 200k locked of y0 = 1M sizes the investor share at 20k of a 100k claim under every basis
+    const payoutsUnder = async (weightBasis: any) => {
+      const v = await setupVault({ fund: 1000000, options: { weightBasis } });
+      const { remainingAccounts, atas } = await pairStreams(streams);
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      return Promise.all(atas.map(tokenBalance));
+    };
+
+    assert.deepEqual(await payoutsUnder({ locked: {} }), [0, 10000, 10000]);
+    assert.deepEqual(await payoutsUnder({ vested: {} }), [20000, 0, 0]);
+    assert.deepEqual(await payoutsUnder({ originalAllocation: {} }), [12000, 4000, 4000]);
+
+    // The lock index only carries locked amounts
+    await expectError(
+      setupVault({ options: { bulkLockIndex: true, weightBasis: { vested: {} } } }),
+      "IncompatibleOptions"
+    );
+  });
+
+  it("lets the initializer claim the authority of a policy without one once", async () => {
+    const v = await setupVault();
+    await program.methods.clearMockPolicyAuthority().accounts({ vault: v.vault, policy: v.policy }).rpc();
+    const newAuthority = Keypair.generate().publicKey;
+    const claim = (claimant: Keypair, signers: PublicKey[]) =>
+      program.methods
+        .claimPolicyAuthority(signers, 1)
+        .accounts({
+          claimant: claimant.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([claimant])
+        .rpc();
+
+    // Only the initializer recorded at init may claim it
+    const stranger = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: stranger.publicKey, lamports: 10_000_000 })
+      )
+    );
+    await expectError(claim(stranger, [stranger.publicKey]), "Unauthorized");
+
+    await claim(payer.payer, [newAuthority]);
+    const policy = await program.account.policy.fetch(v.policy);
+    assert.equal(policy.initializer.toBase58(), payer.publicKey.toBase58());
+    assert.deepEqual(policy.authoritySigners.map((k) => k.toBase58()), [newAuthority.toBase58()]);
+    assert.equal(policy.authorityThreshold, 1);
+
+    await expectError(claim(payer.payer, [payer.publicKey]), "PolicyAuthorityClaimed");
+  });
+
+  it("migrates a raw legacy-sized policy when the vault claims it", async () => {
+    const vaultKp = Keypair.generate();
+    const [policy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("policy"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const [, ownerBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+      program.programId
+    );
+    // The original deployment's Policy, byte for byte: discriminator, vault, y0 (u128),
+    // investor_fee_share_bps (u16), daily_cap (Option<u64>), min_payout, dust_threshold
+    const le = (n: number, bytes: number) => new anchor.BN(n).toArrayLike(Buffer, "le", bytes);
+    const legacyBytes = Buffer.concat([
+      createHash("sha256").update("account:Policy").digest().subarray(0, 8),
+      vaultKp.publicKey.toBuffer(),
+      le(750000, 16),
+      le(3000, 2),
+      Buffer.from([1]),
+      le(500000, 8),
+      le(1000, 8),
+      le(100, 8),
+    ]);
+    assert.equal(legacyBytes.length, 83);
+    await program.methods
+      .writeMockLegacyPolicy(legacyBytes)
+      .accounts({ payer: payer.publicKey, vault: vaultKp.publicKey, policy, systemProgram: SystemProgram.programId })
+      .rpc();
+    const legacyLen = (await provider.connection.getAccountInfo(policy)).data.length;
+    assert.equal(legacyLen, 83);
+
+    // The vault pays for the realloc
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: vaultKp.publicKey, lamports: 100_000_000 })
+      )
+    );
+    const newAuthority = Keypair.generate().publicKey;
+    const claim = (claimant: Keypair) =>
+      program.methods
+        .claimPolicyAuthority([newAuthority], 1)
+        .accounts({
+          claimant: claimant.publicKey,
+          vault: vaultKp.publicKey,
+          policy,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([claimant])
+        .rpc();
+
+    // A legacy policy records no initializer, so even the payer that created it can't claim
+    await expectError(claim(payer.payer), "Unauthorized");
+
+    await claim(vaultKp);
+    const migrated = await provider.connection.getAccountInfo(policy);
+    assert.isAbove(migrated.data.length, legacyLen);
+    const fetched = await program.account.policy.fetch(policy);
+    assert.equal(fetched.vault.toBase58(), vaultKp.publicKey.toBase58());
+    assert.equal(fetched.y0.toString(), "750000");
+    assert.equal(fetched.investorFeeShareBps, 3000);
+    assert.equal(fetched.dailyCap.toString(), "500000");
+    assert.equal(fetched.minPayout.toString(), "1000");
+    assert.equal(fetched.dustThreshold.toString(), "100");
+    assert.deepEqual(fetched.authoritySigners.map((k) => k.toBase58()), [newAuthority.toBase58()]);
+    assert.equal(fetched.authorityThreshold, 1);
+    assert.equal(fetched.ownerPdaVersion, 0);
+    assert.equal(fetched.ownerBump, ownerBump);
+    assert.deepEqual(fetched.heldInvestors, []);
+
+    await expectError(claim(vaultKp), "PolicyAuthorityClaimed");
+  });
+
+  it("migrates a raw legacy policy and progress permissionlessly", async () => {
+    const vaultKp = Keypair.generate();
+    const [policy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("policy"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const [progress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("progress"), vaultKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const le = (n: number, bytes: number) => new anchor.BN(n).toArrayLike(Buffer, "le", bytes);
+    const discriminator = (name: string) =>
+      createHash("sha256").update(`account:${name}`).digest().subarray(0, 8);
+    const legacyPolicy = Buffer.concat([
+      discriminator("Policy"),
+      vaultKp.publicKey.toBuffer(),
+      le(750000, 16),
+      le(3000, 2),
+      Buffer.from([0]),
+      Buffer.alloc(8),
+      le(1000, 8),
+      le(100, 8),
+    ]);
+    // The original Progress: discriminator, vault, day_start_ts, last_distribution_ts,
+    // cumulative_distributed_today, carry_lamports, cursor, treasury_snapshot, then
+    // 20-byte page records (page_index u32, distributed, timestamp) with room for 10
+    const legacyProgress = Buffer.alloc(8 + 32 + 6 * 8 + 4 + 10 * 20);
+    Buffer.concat([
+      discriminator("Progress"),
+      vaultKp.publicKey.toBuffer(),
+      le(86400, 8),
+      le(90000, 8),
+      le(40000, 8),
+      le(7, 8),
+      le(1, 8),
+      le(960000, 8),
+      le(1, 4),
+      le(0, 4),
+      le(40000, 8),
+      le(90000, 8),
+    ]).copy(legacyProgress);
+    await program.methods
+      .writeMockLegacyPolicy(legacyPolicy)
+      .accounts({ payer: payer.publicKey, vault: vaultKp.publicKey, policy, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .writeMockLegacyProgress(legacyProgress)
+      .accounts({ payer: payer.publicKey, vault: vaultKp.publicKey, progress, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    const migrate = () =>
+      program.methods
+        .migrateLegacyAccounts()
+        .accounts({
+          payer: payer.publicKey,
+          vault: vaultKp.publicKey,
+          policy,
+          progress,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    let migrated = (await eventsOf(await migrate())).find((e) => e.name === "legacyAccountsMigrated").data;
+    assert.deepEqual([migrated.policy, migrated.progress], [true, true]);
+
+    const fetchedPolicy = await program.account.policy.fetch(policy);
+    assert.equal(fetchedPolicy.y0.toString(), "750000");
+    assert.isNull(fetchedPolicy.dailyCap);
+    assert.equal(fetchedPolicy.initializer.toBase58(), vaultKp.publicKey.toBase58());
+    const fetchedProgress = await program.account.progress.fetch(progress);
+    assert.equal(fetchedProgress.dayStartTs.toNumber(), 86400);
+    assert.equal(fetchedProgress.carryLamports.toNumber(), 7);
+    assert.equal(fetchedProgress.cursor.toNumber(), 1);
+    assert.equal(fetchedProgress.treasurySnapshot.toNumber(), 960000);
+    assert.equal(fetchedProgress.pageRecordCap, 10);
+    assert.equal(fetchedProgress.pageRecords.length, 1);
+    assert.equal(fetchedProgress.pageRecords[0].distributed.toNumber(), 40000);
+    // The original records were all of the open day
+    assert.equal(fetchedProgress.pageRecords[0].dayTs.toNumber(), 86400);
+
+    // Running it again changes nothing
+    migrated = (await eventsOf(await migrate())).find((e) => e.name === "legacyAccountsMigrated").data;
+    assert.deepEqual([migrated.policy, migrated.progress], [false, false]);
+
+    // The vault, recorded as the initializer, still claims the authority
+    await program.methods
+      .claimPolicyAuthority([payer.publicKey], 1)
+      .accounts({ claimant: vaultKp.publicKey, vault: vaultKp.publicKey, policy, systemProgram: SystemProgram.programId })
+      .signers([vaultKp])
+      .rpc();
+    assert.equal((await program.account.policy.fetch(policy)).authorityThreshold, 1);
+  });
+
+  it("reports the day's crank calls and compute at day close", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    // A replay still cost the keeper a transaction
+    await crank(v, 0, false, remainingAccounts).rpc();
+    const sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const events = await eventsOf(sig);
+    const closed = events.find((e) => e.name === "creatorPayoutDayClosed").data;
+    assert.equal(closed.crankCalls, 3);
+    const lastPage = events.find((e) => e.name === "pageComputeReport").data;
+    assert.ok(closed.computeUnitsConsumed.toNumber() > lastPage.computeUnitsConsumed.toNumber());
+  });
+
+  it("rejects a page index beyond the committed total pages", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    await program.methods
+      .commitTotalPages(2)
+      .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+      .rpc();
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    await crank(v, 1, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc();
+
+    // Page 2 would otherwise pass the cursor check
+    await expectError(
+      crank(v, 2, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(0, 0),
+      }).rpc(),
+      "PageOutOfRange"
+    );
+    assert.equal((await program.account.progress.fetch(v.progress)).cursor.toNumber(), 1);
+  });
+
+  it("sends every fee to the creator in one step with a zero investor share", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      investorFeeShareBps: 0,
+      options: { explicitClaimAccounting: true },
+    });
+    assert.ok((await eventsOf(v.initSig)).find((e) => e.name === "zeroInvestorShare"));
+
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(400000),
+    ]);
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 100000);
+    assert.deepEqual(await Promise.all(atas.map(tokenBalance)), [0, 0]);
+    const events = await eventsOf(sig);
+    assert.notOk(events.find((e) => e.name === "investorDusted"));
+    assert.equal(events.find((e) => e.name === "creatorPayoutDayClosed").data.totalToInvestors.toString(), "0");
+
+    // A non-zero share doesn't trip the event
+    assert.notOk((await eventsOf((await setupVault()).initSig)).find((e) => e.name === "zeroInvestorShare"));
+  });
+
+  it("claims through the fee claim strategy chosen at init", async () => {
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+
+    // PositionFee (default): the honorary position reports the fees
+    const byPosition = await setupVault({ fund: 1000000 });
+    await crank(byPosition, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal((await program.account.progress.fetch(byPosition.progress)).claimedToday.toNumber(), 100000);
+
+    // FeeVault: whatever sits in the pinned fee vault is claimed; the position is ignored
+    const vaultKp = Keypair.generate();
+    const [ownerPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+      program.programId
+    );
+    const feeVault = await createAccount(provider.connection, payer.payer, quoteMint, ownerPda, Keypair.generate());
+    await mintTo(provider.connection, payer.payer, quoteMint, feeVault, payer.publicKey, 70000);
+    const byVault = await setupVault({ vaultKp, feeVault, options: { feeClaimStrategy: { feeVault: {} } } });
+    const { remainingAccounts: vaultAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    await crank(byVault, 0, true, vaultAccounts, {
+      poolFeeVault: feeVault,
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal((await program.account.progress.fetch(byVault.progress)).claimedToday.toNumber(), 70000);
+    assert.equal(await tokenBalance(feeVault), 0);
+    assert.equal(await tokenBalance(atas[0]), 28000);
+
+    // The vault strategy needs a fee vault, and the strategy can't change later
+    await expectError(setupVault({ options: { feeClaimStrategy: { feeVault: {} } } }), "InvalidFeeVault");
+    await expectError(
+      program.methods
+        .updatePolicy(
+          new anchor.BN(1000000),
+          5000,
+          new anchor.BN(1000000),
+          new anchor.BN(1000),
+          new anchor.BN(100),
+          defaultPolicyOptions()
+        )
+        .accounts({ authority: payer.publicKey, vault: byVault.vault, policy: byVault.policy, progress: byVault.progress })
+        .rpc(),
+      "FeeClaimStrategyLocked"
+    );
+  });
+
+  it("stops paying investors once the lifetime cap is reached", async () => {
+    const DAY = 86400;
+    const v = await setupVault({
+      fund: 1000000,
+      options: { explicitClaimAccounting: true, lifetimeInvestorCap: new anchor.BN(50000) },
+    });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    const runDay = async () => {
+      const creatorBefore = await tokenBalance(creatorQuoteAta);
+      const investorBefore = await tokenBalance(atas[0]);
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc({ commitment: "confirmed" });
+      await program.methods
+        .rewindMockDay(new anchor.BN(DAY))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+      return {
+        investor: (await tokenBalance(atas[0])) - investorBefore,
+        creator: (await tokenBalance(creatorQuoteAta)) - creatorBefore,
+        events: await eventsOf(sig),
+      };
+    };
+
+    // Day 1 fits under the cap
+    const day1 = await runDay();
+    assert.equal(day1.investor, 40000);
+    assert.notOk(day1.events.find((e) => e.name === "lifetimeInvestorCapReached"));
+
+    // Day 2 pays what the cap has left; the creator takes the rest
+    const day2 = await runDay();
+    assert.equal(day2.investor, 10000);
+    assert.equal(day2.creator, 90000);
+    const reached = day2.events.find((e) => e.name === "lifetimeInvestorCapReached");
+    assert.equal(reached.data.paidThisPage.toNumber(), 10000);
+
+    // Day 3 goes wholly to the creator
+    const day3 = await runDay();
+    assert.equal(day3.investor, 0);
+    assert.equal(day3.creator, 100000);
+    assert.equal((await program.account.progress.fetch(v.progress)).lifetimeDistributed.toString(), "50000");
+  });
+
+  it("fails day close when investors were paid more than the day claimed", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal((await program.account.progress.fetch(v.progress)).cumulativeDistributedToday.toNumber(), 40000);
+
+    // Pretend page 0 paid out more than its 100k claim
+    await program.methods
+      .setMockDistributedToday(new anchor.BN(150000))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await expectError(
+      crank(v, 1, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(0, 0),
+      }).rpc(),
+      "DistributionExceedsClaimed"
+    );
+  });
+
+  it("sweeps the full treasury to investors in a final distribution", async () => {
+    const v = await setupVault({ fund: 1000000, dailyCap: 1000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(300000),
+      await createMockStream(100000),
+    ]);
+    const finalAccounts = {
+      authority: payer.publicKey,
+      vault: v.vault,
+      policy: v.policy,
+      progress: v.progress,
+      investorFeePosOwnerPda: v.ownerPda,
+      programQuoteTreasury: v.treasury,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    const intruder = Keypair.generate();
+    await expectError(
+      program.methods
+        .finalDistribution()
+        .accounts({ ...finalAccounts, authority: intruder.publicKey })
+        .remainingAccounts(remainingAccounts)
+        .signers([intruder])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    // The daily cap of 1000 doesn't hold back the sweep
+    const sig = await program.methods
+      .finalDistribution()
+      .accounts(finalAccounts)
+      .remainingAccounts(remainingAccounts)
+      .rpc({ commitment: "confirmed" });
+    assert.deepEqual(await Promise.all(atas.map(tokenBalance)), [750000, 250000]);
+    assert.equal(await tokenBalance(v.treasury), 0);
+    const done = (await eventsOf(sig)).find((e) => e.name === "finalDistributionCompleted");
+    assert.equal(done.data.distributed.toNumber(), 1000000);
+    assert.equal(done.data.investorsPaid, 2);
+    assert.equal((await program.account.progress.fetch(v.progress)).treasurySnapshot.toNumber(), 0);
+  });
+
+  it("excludes cancelled streams and treats paused streams per policy", async () => {
+    const streamsWithStatus = async () => {
+      const active = await createMockStream(300000);
+      const cancelled = await createMockStream(400000);
+      const paused = await createMockStream(200000);
+      await program.methods.writeMockStreamStatus({ cancelled: {} }).accounts({ stream: cancelled }).rpc();
+      await program.methods.writeMockStreamStatus({ paused: {} }).accounts({ stream: paused }).rpc();
+      return pairStreams([active, cancelled, paused]);
+    };
+
+    // Paused streams included: 500k locked earns 50% of the claim
+    const including = await setupVault({ fund: 1000000 });
+    const first = await streamsWithStatus();
+    const sig = await crank(including, 0, true, first.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    assert.deepEqual(await Promise.all(first.atas.map(tokenBalance)), [30000, 0, 20000]);
+    assert.notOk((await eventsOf(sig)).find((e) => e.name === "investorDusted"));
+
+    // Paused streams excluded: only the active 300k counts
+    const excluding = await setupVault({ fund: 1000000, options: { pausedStreams: { exclude: {} } } });
+    const second = await streamsWithStatus();
+    await crank(excluding, 0, true, second.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.deepEqual(await Promise.all(second.atas.map(tokenBalance)), [30000, 0, 0]);
+  });
+
+  it("reports the position's unclaimed fees without claiming them", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const position = await createMockPosition(70000, 0);
+    const sig = await program.methods
+      .getPendingFees()
+      .accounts({
+        vault: v.vault,
+        policy: v.policy,
+        honoraryPosition: position,
+        poolFeeVault: null,
+        cpAmmProgram: Keypair.generate().publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const reported = (await eventsOf(sig)).find((e) => e.name === "pendingFeesReported");
+    assert.equal(reported.data.pendingQuote.toNumber(), 70000);
+    assert.equal(reported.data.pendingBase.toNumber(), 0);
+    assert.ok(reported.data.position.equals(position));
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.claimNonce.toNumber(), 0);
+    assert.equal(progress.claimedToday.toNumber(), 0);
+  });
+
+  it("rejects an investor added mid-day until the next day", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const early = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, false, early.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+
+    // A stream opened after page 0 fixed the day's locks
+    const lateStream = await createMockStream(400000);
+    const { lockedReferenceTs } = await program.account.progress.fetch(v.progress);
+    await program.methods
+      .writeMockStreamCreatedAt(lockedReferenceTs.addn(1))
+      .accounts({ stream: lateStream })
+      .rpc();
+    const late = await pairStreams([lateStream]);
+    await expectError(
+      crank(v, 1, true, late.remainingAccounts, {
+        honoraryPosition: await createMockPosition(0, 0),
+      }).rpc(),
+      "InvestorJoinedMidDay"
+    );
+    const closing = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 1, true, closing.remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc();
+
+    // The next day includes it
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 0, true, [...early.remainingAccounts, ...late.remainingAccounts], {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(late.atas[0]), 25000);
+  });
+
+  it("clamps the investor share to the treasury's available balance", async () => {
+    // The mock claim adds no tokens, so the treasury holds less than the 40k investor share
+    const v = await setupVault({ fund: 30000 });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(200000),
+      await createMockStream(200000),
+    ]);
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+
+    const clamped = (await eventsOf(sig)).find((e) => e.name === "payoutClampedToBalance");
+    assert.equal(clamped.data.investorFeeQuote.toNumber(), 40000);
+    assert.equal(clamped.data.available.toNumber(), 30000);
+    assert.deepEqual(await Promise.all(atas.map(tokenBalance)), [15000, 15000]);
+    assert.equal(await tokenBalance(v.treasury), 0);
+  });
+
+  it("pays a wallet with several streams one combined payout", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 15000,
+      options: { investorIdentity: { wallet: {} } },
+    });
+    const walletAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer.payer, quoteMint, Keypair.generate().publicKey)
+    ).address;
+    const walletAccounts = [];
+    for (let i = 0; i < 3; i++) {
+      walletAccounts.push(
+        { pubkey: await createMockStream(100000), isSigner: false, isWritable: false },
+        { pubkey: walletAta, isSigner: false, isWritable: true }
+      );
+    }
+    const other = await pairStreams([await createMockStream(200000)]);
+
+    // 500k locked earns 50k: 10k per wallet stream would each be below min_payout
+    const sig = await crank(v, 0, true, [...walletAccounts, ...other.remainingAccounts], {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    assert.equal(await tokenBalance(walletAta), 30000);
+    assert.equal(await tokenBalance(other.atas[0]), 20000);
+    const events = await eventsOf(sig);
+    assert.equal(events.find((e) => e.name === "locksSnapshot").data.investors, 2);
+    assert.notOk(events.find((e) => e.name === "investorDusted"));
+  });
+
+  it("returns the cursor, day state and remaining cap from the crank", async () => {
+    const v = await setupVault({ fund: 1000000, dailyCap: 100000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    const statusOf = async (sig: string) => {
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const [data] = tx.meta.returnData.data;
+      return new anchor.BorshCoder(program.idl).types.decode("CrankStatus", Buffer.from(data, "base64"));
+    };
+
+    const first = await statusOf(
+      await crank(v, 0, false, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc({ commitment: "confirmed" })
+    );
+    let progress = await program.account.progress.fetch(v.progress);
+    assert.equal(first.cursor.toNumber(), progress.cursor.toNumber());
+    assert.equal(first.dayTs.toNumber(), progress.dayStartTs.toNumber());
+    assert.isFalse(first.dayFinalized);
+    assert.equal(first.remainingCap.toNumber(), 60000);
+
+    const last = await statusOf(
+      await crank(v, 1, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(0, 0),
+      }).rpc({ commitment: "confirmed" })
+    );
+    progress = await program.account.progress.fetch(v.progress);
+    assert.equal(last.cursor.toNumber(), progress.cursor.toNumber());
+    assert.isTrue(last.dayFinalized);
+  });
+
+  it("donates rounding leftovers to the designated beneficiary", async () => {
+    // 700k locked earns 50k, floored to 7142 + 14285 + 28571 = 49998
+    const locks = [100000, 200000, 400000];
+    const run = async (roundingBeneficiary: object) => {
+      const v = await setupVault({ fund: 1000000, options: { roundingBeneficiary } });
+      const { remainingAccounts, atas } = await pairStreams(await Promise.all(locks.map((l) => createMockStream(l))));
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      return Promise.all(atas.map(tokenBalance));
+    };
+
+    assert.deepEqual(await run({ creator: {} }), [7142, 14285, 28571]);
+    assert.deepEqual(await run({ largestLock: {} }), [7142, 14285, 28573]);
+    assert.deepEqual(await run({ largestRemainder: {} }), [7143, 14286, 28571]);
+  });
+
+  it("refuses cranks after an emergency withdrawal until operations resume", async () => {
+    const v = await setupVault({ fund: 300000 });
+    const adminAccounts = { authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress };
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    // Nothing to resume before any emergency
+    await expectError(
+      program.methods.resumeOperations().accounts(adminAccounts).rpc(),
+      "OperationsNotHalted"
+    );
+
+    await program.methods
+      .emergencyWithdraw(new anchor.BN(100000))
+      .accounts({
+        ...adminAccounts,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        destination: creatorQuoteAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    assert.isTrue((await program.account.policy.fetch(v.policy)).emergencyHalted);
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "OperationsHalted"
+    );
+
+    // Only the authority can resume
+    const intruder = Keypair.generate();
+    await expectError(
+      program.methods
+        .resumeOperations()
+        .accounts({ ...adminAccounts, authority: intruder.publicKey })
+        .signers([intruder])
+        .rpc(),
+      "Unauthorized"
+    );
+    const sig = await program.methods.resumeOperations().accounts(adminAccounts).rpc({ commitment: "confirmed" });
+    assert.ok((await eventsOf(sig)).find((e) => e.name === "operationsResumed"));
+
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("holds back the first distribution until the initial delay has passed", async () => {
+    const v = await setupVault({ fund: 1000000, options: { initialDelaySecs: 3600 } });
+    const policy = await program.account.policy.fetch(v.policy);
+    assert.isAbove(policy.initTs.toNumber(), 0);
+
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "InitialDelayNotElapsed"
+    );
+    assert.equal(await tokenBalance(atas[0]), 0);
+    assert.equal((await program.account.progress.fetch(v.progress)).dayStartTs.toNumber(), 0);
+  });
+
+  it("logs the day gate's next allowed time on page 0", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    const { lastDistributionTs } = await program.account.progress.fetch(v.progress);
+
+    // The next page 0 is too early, and its logs say until when
+    const error = await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "DayWindowNotElapsed"
+    );
+    const logs: string[] = error.logs ?? [];
+    const expected = `next_allowed_ts ${lastDistributionTs.addn(86400).toString()} (interval 86400s after the last close)`;
+    assert.ok(logs.some((l) => l.includes(expected)), `no log contains "${expected}"`);
+  });
+
+  it("pays investors in a payout mint at a fixed conversion rate", async () => {
+    const payoutMint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+    const recipient = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      payer.payer,
+      quoteMint,
+      Keypair.generate().publicKey
+    );
+    // 2.5 payout units per quote unit
+    const v = await setupVault({
+      fund: 1000000,
+      options: {
+        payoutMint,
+        conversionRate: new anchor.BN(2500000000),
+        conversionQuoteRecipient: recipient.address,
+        explicitClaimAccounting: true,
+      },
+    });
+    const [payoutTreasury] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payout_treasury"), v.vault.toBuffer(), payoutMint.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createPayoutTreasury()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        investorFeePosOwnerPda: v.ownerPda,
+        payoutTreasury,
+        payoutMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await mintTo(provider.connection, payer.payer, payoutMint, payoutTreasury, payer.publicKey, 1000000);
+
+    const investorAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      payer.payer,
+      payoutMint,
+      Keypair.generate().publicKey
+    );
+    const remainingAccounts = [
+      { pubkey: await createMockStream(400000), isSigner: false, isWritable: false },
+      { pubkey: investorAta.address, isSigner: false, isWritable: true },
+    ];
+
+    try {
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      assert.fail("a payout mint should require the payout treasury");
+    } catch (err) {
+      assert.include(err.toString(), "PayoutTreasuryMissing");
+    }
+
+    const creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+      payoutTreasury,
+      conversionQuoteRecipient: recipient.address,
+    }).rpc({ commitment: "confirmed" });
+
+    // The investor's 40k quote share is paid as 100k payout units; the 40k quote backs it
+    assert.equal(await tokenBalance(investorAta.address), 100000);
+    assert.equal(await tokenBalance(payoutTreasury), 900000);
+    assert.equal(await tokenBalance(recipient.address), 40000);
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 60000);
+    const converted = (await eventsOf(sig)).find((e) => e.name === "payoutConverted").data;
+    assert.ok(converted.payoutMint.equals(payoutMint));
+    assert.equal(converted.quoteAmount.toString(), "40000");
+    assert.equal(converted.payoutAmount.toString(), "100000");
+    const progress = await program.account.progress.fetch(v.progress);
+    // Booked in quote
+    assert.equal(progress.lifetimeDistributed.toString(), "40000");
+  });
+
+  it("tells a partially initialized vault apart from a fully initialized one", async () => {
+    const ensure = (vault: PublicKey) => {
+      const [ownerPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), vault.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+        program.programId
+      );
+      return program.methods
+        .ensureInitialized()
+        .accounts({
+          vault,
+          investorFeePosOwnerPda: ownerPda,
+          programQuoteTreasury: getAssociatedTokenAddressSync(quoteMint, ownerPda, true),
+          poolQuoteMint: quoteMint,
+        })
+        .rpc({ commitment: "confirmed" });
+    };
+
+    try {
+      await ensure(Keypair.generate().publicKey);
+      assert.fail("a fresh vault is not initialized");
+    } catch (err) {
+      assert.include(err.toString(), "NotInitialized");
+    }
+
+    const vaultKp = Keypair.generate();
+    const v = await setupVault({ vaultKp });
+    const sig = await ensure(v.vault);
+    const status = (await eventsOf(sig)).find((e) => e.name === "initializationStatus").data;
+    assert.deepEqual([status.policy, status.progress, status.treasury], [true, true, true]);
+
+    // Policy and progress exist but the treasury is gone
+    await program.methods
+      .closeMockTreasury()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    try {
+      await ensure(v.vault);
+      assert.fail("a vault without its treasury is partially initialized");
+    } catch (err) {
+      assert.include(err.toString(), "PartiallyInitialized");
+    }
+
+    // Re-running init still fails; the repair path doesn't
+    let reinitError;
+    try {
+      await setupVault({ vaultKp });
+    } catch (err) {
+      reinitError = err;
+    }
+    assert.ok(reinitError, "a second init should fail");
+    assert.notInclude(reinitError.toString(), "PartiallyInitialized");
+    await program.methods
+      .createTreasury()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        poolQuoteMint: quoteMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    await ensure(v.vault);
+  });
+
+  it("carries a quote that rounds to zero for every investor into the next day", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      minPayout: 0,
+      options: { explicitClaimAccounting: true, roundedToZero: { carryForward: {} } },
+    });
+    // 1M locked: a 3-unit claim gives investors 1 unit, half a unit each
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(500000),
+      await createMockStream(500000),
+    ]);
+
+    let creatorBefore = await tokenBalance(creatorQuoteAta);
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(3, 0),
+    }).rpc({ commitment: "confirmed" });
+    const rounded = (await eventsOf(sig)).find((e) => e.name === "allPayoutsRoundedToZero").data;
+    assert.equal(rounded.investorFeeQuote.toString(), "1");
+    assert.equal(rounded.lockedTotal.toString(), "1000000");
+    assert.isTrue(rounded.carried);
+    assert.equal(await tokenBalance(atas[0]), 0);
+    assert.equal(await tokenBalance(atas[1]), 0);
+    // The creator gets its 2 units, not the investors' one
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 2);
+    let progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.roundedCarry.toNumber(), 1);
+    assert.equal(progress.carryLamports.toNumber(), 1);
+
+    // The next day's unit joins the held one: 2 units split one each
+    await program.methods
+      .rewindMockDay(new anchor.BN(86400))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    creatorBefore = await tokenBalance(creatorQuoteAta);
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(3, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 1);
+    assert.equal(await tokenBalance(atas[1]), 1);
+    assert.equal((await tokenBalance(creatorQuoteAta)) - creatorBefore, 2);
+    progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.roundedCarry.toNumber(), 0);
+    assert.equal(progress.carryLamports.toNumber(), 0);
+  });
+
+  it("reads the claim accounts ahead of the investor pairs", async () => {
+    const v = await setupVault({ fund: 1000000, options: { claimAccountsFirst: true } });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    const pool = Keypair.generate().publicKey;
+    const cpAmmProgram = Keypair.generate().publicKey;
+    const meta = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: false });
+    // PositionFee: pool_authority, pool, position, position_nft_account, token_a_vault,
+    // token_b_vault, event_authority, cp_amm_program
+    const claimAccounts = (position: PublicKey, poolSlot = pool) => [
+      meta(Keypair.generate().publicKey),
+      meta(poolSlot),
+      meta(position),
+      meta(Keypair.generate().publicKey),
+      meta(Keypair.generate().publicKey),
+      meta(Keypair.generate().publicKey),
+      meta(Keypair.generate().publicKey),
+      meta(cpAmmProgram),
+    ];
+
+    // Investor pairs alone are shorter than the claim segment
+    let honoraryPosition = await createMockPosition(100000, 0);
+    try {
+      await crank(v, 0, true, remainingAccounts, { honoraryPosition, pool, cpAmmProgram }).rpc();
+      assert.fail("the claim accounts should be required");
+    } catch (err) {
+      assert.include(err.toString(), "MissingClaimAccounts");
+    }
+
+    // A claim segment naming another pool is out of order, not an investor
+    try {
+      await crank(
+        v,
+        0,
+        true,
+        [...claimAccounts(honoraryPosition, Keypair.generate().publicKey), ...remainingAccounts],
+        { honoraryPosition, pool, cpAmmProgram }
+      ).rpc();
+      assert.fail("a mismatched claim account should fail");
+    } catch (err) {
+      assert.include(err.toString(), "ClaimAccountMismatch");
+    }
+
+    // Claim accounts, then the investor pair: the page pays as without the claim segment
+    honoraryPosition = await createMockPosition(100000, 0);
+    await crank(v, 0, true, [...claimAccounts(honoraryPosition), ...remainingAccounts], {
+      honoraryPosition,
+      pool,
+      cpAmmProgram,
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.dayLocksCount, 1);
+  });
+
+  it("won't start a day on a stale committed investor set", async () => {
+    const v = await setupVault({ fund: 1000000, options: { snapshotMaxAgeSecs: 3600 } });
+    const commit = () =>
+      program.methods
+        .commitInvestorCount(1)
+        .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+        .rpc();
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    await commit();
+    // Committed two hours ago
+    await program.methods
+      .rewindMockInvestorCommit(new anchor.BN(7200))
+      .accounts({ vault: v.vault, policy: v.policy })
+      .rpc();
+
+    try {
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      assert.fail("a stale investor set should block the day");
+    } catch (err) {
+      assert.include(err.toString(), "StaleSnapshot");
+    }
+
+    // Re-committing refreshes it
+    await commit();
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("reports how far the day has got for dashboards", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const dayProgress = () =>
+      program.methods
+        .getDayProgress()
+        .accounts({ vault: v.vault, policy: v.policy, progress: v.progress })
+        .view();
+    await program.methods
+      .commitTotalPages(4)
+      .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+      .rpc();
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+
+    let report = await dayProgress();
+    assert.isFalse(report.dayInProgress);
+    assert.equal(report.pagesDone, 0);
+    assert.equal(report.percentCompleteBps, 0);
+
+    // Two of four pages, 40k each
+    for (const page of [0, 1]) {
+      await crank(v, page, false, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+    }
+    report = await dayProgress();
+    assert.isTrue(report.dayInProgress);
+    assert.equal(report.cursor.toNumber(), 1);
+    assert.equal(report.pagesDone, 2);
+    assert.equal(report.totalPagesExpected, 4);
+    assert.equal(report.cumulativeDistributedToday.toNumber(), 80000);
+    assert.equal(report.percentCompleteBps, 5000);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.equal(report.dayTs.toString(), progress.dayStartTs.toString());
+  });
+
+  it("migrates the treasury to a new owner PDA and cranks under it", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const [newOwnerPda, newBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), v.vault.toBuffer(), Buffer.from("investor_fee_pos_owner"), Buffer.from([1])],
+      program.programId
+    );
+    const sig = await program.methods
+      .migrateTreasuryAuthority(1)
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        newOwnerPda,
+        programQuoteTreasury: v.treasury,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const migrated = (await eventsOf(sig)).find((e) => e.name === "treasuryAuthorityMigrated").data;
+    assert.equal(migrated.oldOwner.toBase58(), v.ownerPda.toBase58());
+    assert.equal(migrated.newOwner.toBase58(), newOwnerPda.toBase58());
+    assert.equal((await getAccount(provider.connection, v.treasury)).owner.toBase58(), newOwnerPda.toBase58());
+    assert.equal((await program.account.policy.fetch(v.policy)).ownerPdaVersion, 1);
+
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    // The old owner no longer holds the treasury
+    try {
+      await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+      assert.fail("the old owner PDA should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ConstraintSeeds");
+    }
+
+    assert.equal((await program.account.policy.fetch(v.policy)).ownerBump, newBump);
+    const migratedVault = { ...v, ownerPda: newOwnerPda, bump: newBump };
+    // The position was not moved: it is still checked against the version-0 PDA
+    const pool = Keypair.generate().publicKey;
+    await expectError(
+      crank(migratedVault, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0, { pool, owner: newOwnerPda }),
+        pool,
+      }).rpc(),
+      "HonoraryPositionMismatch"
+    );
+    await crank(migratedVault, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0, { pool, owner: v.ownerPda }),
+      pool,
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+
+    // The views and the treasury repair path follow the pinned treasury to its new owner
+    assert.equal((await program.account.policy.fetch(v.policy)).treasury.toBase58(), v.treasury.toBase58());
+    const preview = await previewOf(migratedVault);
+    assert.equal(preview.treasuryBalance.toString(), String(await tokenBalance(v.treasury)));
+    const effective = await program.methods
+      .getEffectivePolicy()
+      .accounts({
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: newOwnerPda,
+        programQuoteTreasury: v.treasury,
+        poolQuoteMint: quoteMint,
+      })
+      .view();
+    assert.equal(effective.y0.toString(), (await program.account.policy.fetch(v.policy)).y0.toString());
+    const createTreasury = (owner: PublicKey, treasury: PublicKey) =>
+      program.methods
+        .createTreasury()
+        .accounts({
+          payer: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          investorFeePosOwnerPda: owner,
+          programQuoteTreasury: treasury,
+          poolQuoteMint: quoteMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    await createTreasury(newOwnerPda, v.treasury);
+    await expectError(createTreasury(v.ownerPda, v.treasury), "OwnerPdaMismatch");
+    // The new owner's own ATA is not the treasury
+    await expectError(
+      createTreasury(newOwnerPda, getAssociatedTokenAddressSync(quoteMint, newOwnerPda, true)),
+      "TreasuryMismatch"
+    );
+
+    // An escrow created after the migration is held by the new owner, never the old one
+    const [dayEscrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("day_escrow"), v.vault.toBuffer()],
+      program.programId
+    );
+    const createEscrow = (owner: PublicKey) =>
+      program.methods
+        .createDayEscrow()
+        .accounts({
+          payer: payer.publicKey,
+          vault: v.vault,
+          policy: v.policy,
+          investorFeePosOwnerPda: owner,
+          dayEscrow,
+          poolQuoteMint: quoteMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    await expectError(createEscrow(v.ownerPda), "ConstraintSeeds");
+    await createEscrow(newOwnerPda);
+    assert.equal((await getAccount(provider.connection, dayEscrow)).owner.toBase58(), newOwnerPda.toBase58());
+  });
+
+  it("rejects a quote mint that is not the pool's fee-accruing side", async () => {
+    // Mock pool collecting fees in token B only (cp-amm CollectFeeMode::OnlyB)
+    const createMockPool = async (tokenA: PublicKey, tokenB: PublicKey) => {
+      const pool = Keypair.generate();
+      const accountSize = 73;
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: payer.publicKey,
+            newAccountPubkey: pool.publicKey,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(accountSize),
+            space: accountSize,
+            programId: program.programId,
+          })
+        ),
+        [payer.payer, pool]
+      );
+      await program.methods.writeMockPool(tokenA, tokenB, 1).accounts({ pool: pool.publicKey }).rpc();
+      return pool.publicKey;
+    };
+
+    // The pool accrues fees in the configured base mint
+    const swapped = await createMockPool(quoteMint, baseMint);
+    try {
+      await setupVault({ pool: swapped });
+      assert.fail("quote and base swapped relative to the pool should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "PoolTokenOrderMismatch");
+    }
+
+    const v = await setupVault({ pool: await createMockPool(baseMint, quoteMint) });
+    assert.equal((await program.account.policy.fetch(v.policy)).vault.toBase58(), v.vault.toBase58());
+  });
+
+  it("names the position that leaked base fees in strict mode", async () => {
+    const clean = await createMockPosition(100000, 0);
+    const leaking = await createMockPosition(100000, 5000);
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+
+    await crank(await setupVault({ fund: 1000000 }), 0, true, remainingAccounts, {
+      honoraryPosition: clean,
+    }).rpc();
+    const error = await expectError(
+      crank(await setupVault({ fund: 1000000 }), 0, true, remainingAccounts, {
+        honoraryPosition: leaking,
+      }).rpc(),
+      "BaseFeesObserved"
+    );
+    const logs: string[] = error.logs ?? [];
+    assert.ok(logs.some((l) => l.includes(`Position ${leaking.toBase58()}`) && l.includes("5000 base fees")));
+    assert.notOk(logs.some((l) => l.includes(`Position ${clean.toBase58()}`)));
+  });
+
+  it("retains a share of each day's payouts and releases it in the final distribution", async () => {
+    const DAY = 86400;
+    const v = await setupVault({
+      fund: 1000000,
+      options: { retentionBps: 1000, explicitClaimAccounting: true },
+    });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    // Two days of 40k each: 10% is withheld
+    for (const day of [1, 2]) {
+      const sig = await crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc({ commitment: "confirmed" });
+      const retained = (await eventsOf(sig)).find((e) => e.name === "payoutsRetained").data;
+      assert.equal(retained.amount.toNumber(), 4000);
+      assert.equal(retained.retainedTotal.toNumber(), 4000 * day);
+      assert.equal(await tokenBalance(atas[0]), 36000 * day);
+      await program.methods
+        .rewindMockDay(new anchor.BN(DAY))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+    }
+    let progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.retainedTotal.toNumber(), 8000);
+    // Withheld payouts still count as distributed
+    assert.equal(progress.lifetimeDistributed.toString(), "80000");
+
+    const sig = await program.methods
+      .finalDistribution()
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+        investorFeePosOwnerPda: v.ownerPda,
+        programQuoteTreasury: v.treasury,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc({ commitment: "confirmed" });
+    const done = (await eventsOf(sig)).find((e) => e.name === "finalDistributionCompleted").data;
+    assert.equal(done.retentionReleased.toNumber(), 8000);
+    assert.equal(await tokenBalance(atas[0]), 72000 + done.distributed.toNumber());
+    progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.retainedTotal.toNumber(), 0);
+    // Only the fresh part of the sweep adds to the lifetime total
+    assert.equal(progress.lifetimeDistributed.toString(), (80000 + done.distributed.toNumber() - 8000).toString());
+  });
+
+  it("rejects an honorary position of another pool at crank time", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const pool = Keypair.generate().publicKey;
+    const positionFor = (positionPool: PublicKey, owner: PublicKey) =>
+      createMockPosition(100000, 0, { pool: positionPool, owner });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    for (const honoraryPosition of [
+      await positionFor(Keypair.generate().publicKey, v.ownerPda),
+      await positionFor(pool, Keypair.generate().publicKey),
+    ]) {
+      try {
+        await crank(v, 0, true, remainingAccounts, { honoraryPosition, pool }).rpc();
+        assert.fail("a position of another pool or owner should be rejected");
+      } catch (err) {
+        assert.include(err.toString(), "HonoraryPositionMismatch");
+      }
+    }
+
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await positionFor(pool, v.ownerPda),
+      pool,
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("runs an open day on the policy captured at its page 0", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const first = await pairStreams([await createMockStream(400000)]);
+    const second = await pairStreams([await createMockStream(400000)]);
+
+    // 400k of 1M locked -> 40% under the 50% share
+    await crank(v, 0, false, first.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(first.atas[0]), 40000);
+
+    await program.methods
+      .updatePolicy(
+        new anchor.BN(1000000),
+        2000,
+        new anchor.BN(1000000),
+        new anchor.BN(1000),
+        new anchor.BN(100),
+        defaultPolicyOptions()
+      )
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+      })
+      .rpc();
+    assert.equal((await program.account.policy.fetch(v.policy)).investorFeeShareBps, 2000);
+
+    // The rest of the day still pays the 50% share
+    await crank(v, 1, true, second.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(second.atas[0]), 40000);
+    let progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.dayPolicy.investorFeeShareBps, 5000);
+
+    // The next day picks up the 20% share
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 0, true, first.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(first.atas[0]), 60000);
+    progressAccount = await program.account.progress.fetch(v.progress);
+    assert.equal(progressAccount.dayPolicy.investorFeeShareBps, 2000);
+  });
+
+  it("rejects a page whose investors fall outside the committed set", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    await program.methods
+      .commitInvestorCount(2)
+      .accounts({
+        authority: payer.publicKey,
+        vault: v.vault,
+        policy: v.policy,
+        progress: v.progress,
+      })
+      .rpc();
+    const committed = await pairStreams([
+      await createMockStream(200000),
+      await createMockStream(200000),
+    ]);
+    const outsider = await pairStreams([await createMockStream(200000)]);
+
+    // Three investors against two committed fail before anything is paid
+    await expectError(
+      crank(v, 0, false, [...committed.remainingAccounts, ...outsider.remainingAccounts], {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "InvestorSetMismatch"
+    );
+    assert.equal(await tokenBalance(committed.atas[0]), 0);
+    assert.equal((await program.account.progress.fetch(v.progress)).lastDistributionTs.toNumber(), 0);
+
+    // Once the committed pair is covered, a later page with the outsider fails too
+    await crank(v, 0, false, committed.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    await expectError(
+      crank(v, 1, true, outsider.remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "InvestorSetMismatch"
+    );
+    assert.equal(await tokenBalance(outsider.atas[0]), 0);
+    assert.equal((await program.account.progress.fetch(v.progress)).dayLocksCount, 2);
+  });
+
+  it("carries a claim below min_claim_to_distribute instead of dusting it", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      options: { minClaimToDistribute: new anchor.BN(1000) },
+    });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+    ]);
+
+    // The 10-unit small-fees claim doesn't start the day
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(10, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    const below = events.find((e) => e.name === "claimBelowMinimum");
+    assert.ok(below, "ClaimBelowMinimum should be emitted");
+    assert.equal(below.data.claimed.toString(), "10");
+    assert.equal(below.data.carriedTotal.toString(), "10");
+    assert.equal(below.data.minClaimToDistribute.toString(), "1000");
+    assert.notOk(events.find((e) => e.name === "investorDusted"));
+    assert.notOk(events.find((e) => e.name === "investorPayoutPage"));
+    assert.equal(await tokenBalance(atas[0]), 0);
+    let progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.externalClaimedPending.toString(), "10");
+    assert.equal(progress.lastDistributionTs.toNumber(), 0);
+
+    // The next page 0 distributes it with its own claim: 40% of 100,010
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40004);
+    progress = await program.account.progress.fetch(v.progress);
+    assert.equal(progress.externalClaimedPending.toString(), "0");
+    assert.ok(progress.lastDistributionTs.toNumber() > 0);
+  });
+
+  it("stores the owner PDA's canonical bump at init for the crank to sign with", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const [derived, derivedBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), v.vault.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+      program.programId
+    );
+    assert.equal(derived.toBase58(), v.ownerPda.toBase58());
+    const policy = await program.account.policy.fetch(v.policy);
+    assert.equal(policy.ownerBump, derivedBump);
+
+    // The crank takes no bump and signs the treasury transfers with the stored one
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("emits a Merkle root of the day's payouts that proves each one", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const page0 = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(200000),
+      await createMockStream(100000),
+    ]);
+    const page1 = await pairStreams([await createMockStream(300000)]);
+    await crank(v, 0, false, page0.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    const sig = await crank(v, 1, true, page1.remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const closed = (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data;
+
+    // Same tree as `payout_tree`: sha256 leaves/nodes with 0x00/0x01 prefixes, depth 16,
+    // padded with zero subtrees
+    const DEPTH = 16;
+    const sha = (...parts: Buffer[]) => createHash("sha256").update(Buffer.concat(parts)).digest();
+    const leafOf = (ata: PublicKey, amount: number) => {
+      const le = Buffer.alloc(8);
+      le.writeBigUInt64LE(BigInt(amount));
+      return sha(Buffer.from([0]), ata.toBuffer(), le);
+    };
+    const nodeOf = (left: Buffer, right: Buffer) => sha(Buffer.from([1]), left, right);
+    const zeros = [Buffer.alloc(32)];
+    for (let level = 1; level < DEPTH; level++) zeros.push(nodeOf(zeros[level - 1], zeros[level - 1]));
+
+    const atas = [...page0.atas, ...page1.atas];
+    const amounts = await Promise.all(atas.map((ata) => tokenBalance(ata)));
+    const levels: Buffer[][] = [atas.map((ata, i) => leafOf(ata, amounts[i]))];
+    for (let level = 0; level < DEPTH; level++) {
+      const below = levels[level];
+      const above: Buffer[] = [];
+      for (let i = 0; i < below.length; i += 2) above.push(nodeOf(below[i], below[i + 1] ?? zeros[level]));
+      levels.push(above);
+    }
+    assert.equal(closed.payoutsCount, 4);
+    assert.equal(Buffer.from(closed.payoutsRoot).toString("hex"), levels[DEPTH][0].toString("hex"));
+
+    // Proof for the third investor: one sibling per level
+    const index = 2;
+    const proof = levels.slice(0, DEPTH).map((nodes, level) => nodes[(index >> level) ^ 1] ?? zeros[level]);
+    let node = leafOf(atas[index], amounts[index]);
+    proof.forEach((sibling, level) => {
+      node = (index >> level) & 1 ? nodeOf(sibling, node) : nodeOf(node, sibling);
+    });
+    assert.equal(node.toString("hex"), Buffer.from(closed.payoutsRoot).toString("hex"));
+    // A claimed amount other than the paid one doesn't verify
+    assert.notEqual(leafOf(atas[index], amounts[index] + 1).toString("hex"), levels[0][index].toString("hex"));
+  });
+
+  it("fails a claim whose reported amount doesn't match the treasury delta", async () => {
+    // A fee vault held by the owner PDA pays the mock claim for real, up to its balance
+    const vaultKp = Keypair.generate();
+    const [ownerPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vaultKp.publicKey.toBuffer(), Buffer.from("investor_fee_pos_owner")],
+      program.programId
+    );
+    const feeVault = await createAccount(provider.connection, payer.payer, quoteMint, ownerPda, Keypair.generate());
+    await mintTo(provider.connection, payer.payer, quoteMint, feeVault, payer.publicKey, 100000);
+    const v = await setupVault({ vaultKp, feeVault, options: { claimToleranceBps: 100 } });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    // The position reports the claim in units 1,000x smaller than the ones that moved
+    await expectError(
+      crank(v, 0, true, remainingAccounts, {
+        poolFeeVault: feeVault,
+        honoraryPosition: await createMockPosition(100000000, 0),
+      }).rpc(),
+      "ClaimDiscrepancy"
+    );
+    assert.equal(await tokenBalance(feeVault), 100000);
+    assert.equal(await tokenBalance(atas[0]), 0);
+
+    // A report matching what moved distributes as usual
+    await crank(v, 0, true, remainingAccounts, {
+      poolFeeVault: feeVault,
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal(await tokenBalance(feeVault), 0);
+    assert.equal(await tokenBalance(atas[0]), 40000);
+  });
+
+  it("leaves lockers below min_locked_to_participate out of the split", async () => {
+    const v = await setupVault({
+      fund: 1000000,
+      options: { minLockedToParticipate: new anchor.BN(100000) },
+    });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(300000),
+      await createMockStream(50000),
+    ]);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const events = await eventsOf(sig);
+    // 700k eligible of 1M (the 50k locker doesn't count), capped by the 50% share
+    const locks = events.find((e) => e.name === "locksSnapshot").data;
+    assert.equal(locks.pageLockedTotal.toString(), "700000");
+    // The 50k locker's would-be share goes to the other two: 50,000 split 4:3
+    assert.equal(await tokenBalance(atas[0]), 28571);
+    assert.equal(await tokenBalance(atas[1]), 21428);
+    assert.equal(await tokenBalance(atas[2]), 0);
+    assert.notOk(events.find((e) => e.name === "investorDusted"));
+  });
+
+  it("gates the next day at exactly 24h after the close on the mock clock", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+    const elapse = (secs: number) =>
+      program.methods
+        .setMockDayElapsed(new anchor.BN(secs))
+        .accounts({ vault: v.vault, progress: v.progress })
+        .rpc();
+    // A fresh mock position each time keeps the retried transactions distinct
+    const nextDay = async () =>
+      crank(v, 0, true, remainingAccounts, {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc();
+    await nextDay();
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    const closedAt = (await program.account.progress.fetch(v.progress)).lastDistributionTs.toNumber();
+
+    // A minute short of the window: still gated
+    await elapse(DAY - 60);
+    const progress = await program.account.progress.fetch(v.progress);
+    assert.isBelow(progress.lastDistributionTs.toNumber(), closedAt);
+    await expectError(nextDay(), "DayWindowNotElapsed");
+
+    // Exactly at the window: the next day starts
+    await elapse(DAY);
+    await nextDay();
+    assert.equal(await tokenBalance(atas[0]), 80000);
+  });
+
+  it("enforces a whole-token daily cap in raw quote units", async () => {
+    // 1 token of the 6-decimal quote mint is 1,000,000 raw units
+    const v = await setupVault({
+      fund: 10000000,
+      dailyCap: null,
+      options: { dailyCapTokens: new anchor.BN(1) },
+    });
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(400000)]);
+
+    const sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(5000000, 0),
+    }).rpc({ commitment: "confirmed" });
+    // 40% of 5,000,000 would be 2,000,000; the cap holds it to 1,000,000
+    assert.equal(await tokenBalance(atas[0]), 1000000);
+    const exhausted = (await eventsOf(sig)).find((e) => e.name === "dailyCapExhausted");
+    assert.equal(exhausted.data.dailyCap.toString(), "1000000");
+
+    // One cap or the other, not both
+    await expectError(
+      setupVault({ dailyCap: 1000000, options: { dailyCapTokens: new anchor.BN(1) } }),
+      "IncompatibleOptions"
+    );
+  });
+
+  it("resolves the effective policy from the day's terms, the oracle and the gate", async () => {
+    const DAY = 86400;
+    const oracle = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: oracle.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(10),
+          space: 10,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, oracle]
+    );
+    await program.methods.writeMockShareOracle(9000).accounts({ oracle: oracle.publicKey }).rpc();
+    const v = await setupVault({
+      fund: 1000000,
+      dailyCap: null,
+      options: {
+        dailyCapTokens: new anchor.BN(1),
+        shareOracle: oracle.publicKey,
+        shareOracleProgram: program.programId,
+        oracleMinBps: 1000,
+        oracleMaxBps: 3000,
+      },
+    });
+    const effective = () =>
+      program.methods
+        .getEffectivePolicy()
+        .accounts({
+          vault: v.vault,
+          policy: v.policy,
+          progress: v.progress,
+          investorFeePosOwnerPda: v.ownerPda,
+          programQuoteTreasury: v.treasury,
+          poolQuoteMint: quoteMint,
+          shareOracle: oracle.publicKey,
+        })
+        .view();
+
+    // Before the first day: oracle reading clamped to 3000, 1 token = 1,000,000 raw
+    let resolved = await effective();
+    assert.isFalse(resolved.dayTermsCaptured);
+    assert.equal(resolved.shareBps, 3000);
+    assert.equal(resolved.dailyCap.toString(), "1000000");
+    assert.equal(resolved.remainingDailyCap.toString(), "1000000");
+    assert.isNull(resolved.remainingLifetimeCap);
+    assert.equal(resolved.nextDayTs.toNumber(), 0);
+    assert.isTrue(resolved.newDayAllowed);
+
+    const { remainingAccounts, atas } = await pairStreams([await createMockStream(1000000)]);
+    await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+      shareOracle: oracle.publicKey,
+    }).rpc();
+    assert.equal(await tokenBalance(atas[0]), 30000);
+
+    // Closed: 30,000 of the cap used, the next day a full window after the close
+    const closedAt = (await program.account.progress.fetch(v.progress)).lastDistributionTs.toNumber();
+    resolved = await effective();
+    assert.equal(resolved.remainingDailyCap.toString(), "970000");
+    assert.equal(resolved.nextDayTs.toNumber(), closedAt + DAY);
+    assert.isFalse(resolved.newDayAllowed);
+
+    // A used-up cap leaves no share, and pausing new days keeps the gate shut
+    await program.methods
+      .setMockDistributedToday(new anchor.BN(1000000))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    resolved = await effective();
+    assert.equal(resolved.shareBps, 0);
+    assert.equal(resolved.remainingDailyCap.toString(), "0");
+    assert.isTrue(resolved.newDayAllowed);
+    await program.methods
+      .setNewDaysPaused(true)
+      .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+      .rpc();
+    assert.isFalse((await effective()).newDayAllowed);
+  });
+
+  it("pays each investor through the ATA paired with its stream on a large page", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    // Nine investors locking 10k..90k: 450k of 1M, so 45,000 of the claim, 1,000 per 10k
+    const streams = [];
+    for (let i = 1; i <= 9; i++) {
+      streams.push(await createMockStream(10000 * i));
+    }
+    const { remainingAccounts, atas } = await pairStreams(streams);
+    // Two streams paid to the same ATA: each pair's own account is used, twice
+    remainingAccounts[17] = remainingAccounts[1];
+
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    assert.equal(await tokenBalance(atas[0]), 1000 + 9000);
+    for (let i = 1; i < 8; i++) {
+      assert.equal(await tokenBalance(atas[i]), 1000 * (i + 1));
+    }
+    assert.equal(await tokenBalance(atas[8]), 0);
+    // One transfer per pair
+    const report = (await eventsOf(sig)).find((e) => e.name === "pageComputeReport").data;
+    assert.equal(report.investors, 9);
+    assert.equal(report.transfers, 9);
+  });
+
+  it("transfers to the parsed ATA accounts for grouped wallets and bulk indexes", async () => {
+    // Two streams of one wallet, grouped onto the first pair's ATA
+    const wallets = await setupVault({ fund: 1000000, options: { investorIdentity: { wallet: {} } } });
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(300000),
+      await createMockStream(100000),
+      await createMockStream(100000),
+    ]);
+    remainingAccounts[3] = remainingAccounts[1];
+    await crank(wallets, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    // 500k locked: 50,000 split 4:1 between the two wallets
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(await tokenBalance(atas[1]), 0);
+    assert.equal(await tokenBalance(atas[2]), 10000);
+
+    // ATAs passed in the reverse of the index's order are each paid from their own slot
+    const bulk = await pairStreams([await createMockStream(400000), await createMockStream(100000)]);
+    const entries = [
+      { stream: bulk.streams[0], investorAta: bulk.atas[0], lockedAmount: new anchor.BN(400000) },
+      { stream: bulk.streams[1], investorAta: bulk.atas[1], lockedAmount: new anchor.BN(100000) },
+    ];
+    const lockIndex = Keypair.generate();
+    const space = 8 + 4 + entries.length * 72;
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: lockIndex.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+          space,
+          programId: program.programId,
+        })
+      ),
+      [payer.payer, lockIndex]
+    );
+    await program.methods.writeMockLockIndex(entries).accounts({ lockIndex: lockIndex.publicKey }).rpc();
+    const v = await setupVault({ fund: 1000000, options: { bulkLockIndex: true } });
+    await crank(
+      v,
+      0,
+      false,
+      [
+        { pubkey: lockIndex.publicKey, isSigner: false, isWritable: false },
+        { pubkey: bulk.atas[1], isSigner: false, isWritable: true },
+        { pubkey: bulk.atas[0], isSigner: false, isWritable: true },
+      ],
+      { honoraryPosition: await createMockPosition(20000, 0) }
+    ).rpc();
+    assert.equal(await tokenBalance(bulk.atas[0]), 8000);
+    assert.equal(await tokenBalance(bulk.atas[1]), 2000);
+  });
+
+  it("flags a page where most investors are dusted", async () => {
+    const v = await setupVault({ fund: 1000000, options: { mostlyDustBps: 5000 } });
+    // 403k locked: 40,300 to split, and the 1k lockers' 100 each fall below min_payout
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(400000),
+      await createMockStream(1000),
+      await createMockStream(1000),
+      await createMockStream(1000),
+    ]);
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const flagged = (await eventsOf(sig)).find((e) => e.name === "pageMostlyDust");
+    assert.ok(flagged, "PageMostlyDust not emitted");
+    assert.equal(flagged.data.investors, 4);
+    assert.equal(flagged.data.dusted, 3);
+    assert.equal(flagged.data.minPayout.toString(), "1000");
+    // Not an error: the page still pays the investor above min_payout
+    assert.equal(await tokenBalance(atas[0]), 40000);
+    assert.equal(await tokenBalance(atas[1]), 0);
+  });
+
+  it("records each day's policy parameters in its day-close event", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const { remainingAccounts } = await pairStreams([await createMockStream(400000)]);
+    const update = (shareBps: number, cap: number, minPayout: number) =>
+      program.methods
+        .updatePolicy(
+          new anchor.BN(1000000),
+          shareBps,
+          new anchor.BN(cap),
+          new anchor.BN(minPayout),
+          new anchor.BN(100),
+          defaultPolicyOptions()
+        )
+        .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+        .rpc();
+    const termsOf = async (sig: string) =>
+      (await eventsOf(sig)).find((e) => e.name === "creatorPayoutDayClosed").data.terms;
+
+    // Day 1 on the init parameters
+    let sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    let terms = await termsOf(sig);
+    assert.equal(terms.investorFeeShareBps, 5000);
+    assert.equal(terms.dailyCap.toString(), "1000000");
+    assert.equal(terms.minPayout.toString(), "1000");
+    assert.equal(terms.y0.toString(), "1000000");
+
+    // Day 2 starts on the updated terms; a mid-day update waits for day 3
+    await update(3000, 500000, 500);
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    await update(2000, 250000, 200);
+    sig = await crank(v, 1, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(0, 0),
+    }).rpc({ commitment: "confirmed" });
+    terms = await termsOf(sig);
+    assert.equal(terms.investorFeeShareBps, 3000);
+    assert.equal(terms.dailyCap.toString(), "500000");
+    assert.equal(terms.minPayout.toString(), "500");
+
+    // Day 3 on the mid-day update
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    sig = await crank(v, 0, true, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    terms = await termsOf(sig);
+    assert.equal(terms.investorFeeShareBps, 2000);
+    assert.equal(terms.dailyCap.toString(), "250000");
+    assert.equal(terms.minPayout.toString(), "200");
+  });
+
+  it("recomputes a page's f_locked_bps and eligible share for verification", async () => {
+    const v = await setupVault({ fund: 1000000 });
+    const lockedShare = (lockedTotal: anchor.BN) =>
+      program.methods
+        .getLockedShare(lockedTotal)
+        .accounts({ vault: v.vault, policy: v.policy, progress: v.progress })
+        .view();
+    const { remainingAccounts, atas } = await pairStreams([
+      await createMockStream(250000),
+      await createMockStream(150000),
+    ]);
+    const sig = await crank(v, 0, false, remainingAccounts, {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc({ commitment: "confirmed" });
+    const locks = (await eventsOf(sig)).find((e) => e.name === "locksSnapshot").data;
+    const page = (await eventsOf(sig)).find((e) => e.name === "investorPayoutPage").data;
+
+    // 400k of 1M locked: f_locked 40% caps the 50% share
+    const share = await lockedShare(locks.pageLockedTotal);
+    assert.equal(share.y0.toString(), "1000000");
+    assert.equal(share.fLockedBps, 4000);
+    assert.equal(share.shareBps, 5000);
+    assert.equal(share.eligibleBps, 4000);
+    assert.deepEqual(share.eligibleTiers, []);
+    // The crank paid exactly claimed * eligible_bps / 10000
+    const paid = (await tokenBalance(atas[0])) + (await tokenBalance(atas[1]));
+    assert.equal(paid, (100000 * share.eligibleBps) / 10000);
+    assert.equal(page.pageTotalPayout.toNumber(), paid);
+
+    // Above the share, and past the baseline
+    assert.equal((await lockedShare(new anchor.BN(700000))).eligibleBps, 5000);
+    const saturated = await lockedShare(new anchor.BN(3000000));
+    assert.equal(saturated.fLockedBps, 10000);
+    assert.equal(saturated.eligibleBps, 5000);
+  });
+
+  it("holds one investor's payouts on their pending withdrawal until released", async () => {
+    const DAY = 86400;
+    const v = await setupVault({ fund: 1000000 });
+    const investor = Keypair.generate();
+    const investorAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer.payer, quoteMint, investor.publicKey)
+    ).address;
+    const [pending] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_withdrawal"), v.vault.toBuffer(), investorAta.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openPendingWithdrawal()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        investorAta,
+        pendingWithdrawal: pending,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const setHold = (held: boolean) =>
+      program.methods
+        .setInvestorHold(investorAta, held)
+        .accounts({ authority: payer.publicKey, vault: v.vault, policy: v.policy, progress: v.progress })
+        .rpc();
+    await setHold(true);
+    assert.deepEqual(
+      (await program.account.policy.fetch(v.policy)).heldInvestors.map((k) => k.toBase58()),
+      [investorAta.toBase58()]
+    );
+
+    const heldStream = await createMockStream(400000, 0, 0, investorAta);
+    const other = await pairStreams([await createMockStream(100000)]);
+    const page = (dest: PublicKey) => [
+      { pubkey: heldStream, isSigner: false, isWritable: false },
+      { pubkey: dest, isSigner: false, isWritable: true },
+      ...other.remainingAccounts,
+    ];
+    // Paying the held ATA directly is refused
+    await expectError(
+      crank(v, 0, true, page(investorAta), {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "InvestorOnHold"
+    );
+
+    // Nor can the held payout be rerouted to another investor's pending withdrawal
+    const [otherPending] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_withdrawal"), v.vault.toBuffer(), other.atas[0].toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openPendingWithdrawal()
+      .accounts({
+        payer: payer.publicKey,
+        vault: v.vault,
+        investorAta: other.atas[0],
+        pendingWithdrawal: otherPending,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await expectError(
+      crank(v, 0, true, page(otherPending), {
+        honoraryPosition: await createMockPosition(100000, 0),
+      }).rpc(),
+      "InvestorDestinationMismatch"
+    );
+    assert.equal((await program.account.pendingWithdrawal.fetch(otherPending)).amount.toString(), "0");
+
+    // Still weighed in locked_total (500k): its 40,000 is queued, the other investor is paid
+    await crank(v, 0, true, page(pending), {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal((await program.account.pendingWithdrawal.fetch(pending)).amount.toString(), "40000");
+    assert.equal(await tokenBalance(investorAta), 0);
+    assert.equal(await tokenBalance(other.atas[0]), 10000);
+
+    // A second day accrues on top
+    await program.methods
+      .rewindMockDay(new anchor.BN(DAY))
+      .accounts({ vault: v.vault, progress: v.progress })
+      .rpc();
+    await crank(v, 0, true, page(pending), {
+      honoraryPosition: await createMockPosition(100000, 0),
+    }).rpc();
+    assert.equal((await program.account.pendingWithdrawal.fetch(pending)).amount.toString(), "80000");
+
+    const claim = () =>
+      program.methods
+        .claimPending()
+        .accounts({
+          investor: investor.publicKey,
+          vault: v.vault,
+          progress: v.progress,
+          pendingWithdrawal: pending,
+          investorAta,
+          investorFeePosOwnerPda: v.ownerPda,
+          programQuoteTreasury: v.treasury,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([investor])
+        .rpc();
+    await expectError(claim(), "InvestorOnHold");
+
+    // Released: the accrued payouts are claimable
+    await setHold(false);
+    await claim();
+    assert.equal(await tokenBalance(investorAta), 80000);
+    assert.equal((await program.account.pendingWithdrawal.fetch(pending)).amount.toString(), "0");
+    assert.lengthOf((await program.account.policy.fetch(v.policy)).heldInvestors, 0);
+  });
 });